@@ -0,0 +1,93 @@
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+lazy_static! {
+    /// 缩写：在行编辑器中输入空格或回车时就地展开，展开后的命令进入历史
+    static ref ABBREVIATIONS: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+}
+
+pub fn get(name: &str) -> Option<String> {
+    ABBREVIATIONS.read().unwrap().get(name).cloned()
+}
+
+pub fn set(name: &str, expansion: &str) {
+    ABBREVIATIONS
+        .write()
+        .unwrap()
+        .insert(name.to_string(), expansion.to_string());
+}
+
+/// 删除缩写，返回它之前是否存在
+pub fn remove(name: &str) -> bool {
+    ABBREVIATIONS.write().unwrap().remove(name).is_some()
+}
+
+/// 按名称排序的全部缩写
+pub fn all() -> Vec<(String, String)> {
+    ABBREVIATIONS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(name, expansion)| (name.clone(), expansion.clone()))
+        .collect()
+}
+
+/// 缩写名称与别名一样不能含有空白、引号或 shell 元字符
+pub fn is_valid_name(name: &str) -> bool {
+    crate::alias::is_valid_name(name) && !name.starts_with('-')
+}
+
+/// 展开光标前紧邻的单词：单词须位于命令名的位置，返回新的行与光标位置
+pub fn expand_before_cursor(line: &str, cursor: usize) -> Option<(String, usize)> {
+    let before = &line[..cursor];
+    let start = before
+        .rfind(|c: char| c.is_whitespace() || matches!(c, ';' | '|' | '&' | '(' | '{'))
+        .map_or(0, |i| i + before[i..].chars().next().unwrap().len_utf8());
+    let word = &before[start..];
+    if word.is_empty() {
+        return None;
+    }
+    let prefix = before[..start].trim_end();
+    let at_command_start = prefix.is_empty()
+        || prefix.ends_with([';', '|', '&', '(', '{'])
+        || matches!(
+            prefix.rsplit(char::is_whitespace).next(),
+            Some("if" | "then" | "elif" | "else" | "while" | "until" | "do")
+        );
+    if !at_command_start {
+        return None;
+    }
+    let expansion = get(word)?;
+    let mut expanded = String::with_capacity(line.len() + expansion.len());
+    expanded.push_str(&line[..start]);
+    expanded.push_str(&expansion);
+    expanded.push_str(&line[cursor..]);
+    Some((expanded, start + expansion.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abbr_expand_at_command_start() {
+        set("t_gco", "git checkout");
+        assert_eq!(
+            expand_before_cursor("t_gco", 5),
+            Some(("git checkout".to_string(), 12))
+        );
+        assert_eq!(
+            expand_before_cursor("make && t_gco main", 13),
+            Some(("make && git checkout main".to_string(), 20))
+        );
+        assert_eq!(
+            expand_before_cursor("if t_gco", 8),
+            Some(("if git checkout".to_string(), 15))
+        );
+        // 参数位置的单词与部分匹配不会展开
+        assert_eq!(expand_before_cursor("echo t_gco", 10), None);
+        assert_eq!(expand_before_cursor("t_gc", 4), None);
+        assert_eq!(expand_before_cursor("t_gco ", 6), None);
+    }
+}
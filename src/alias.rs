@@ -0,0 +1,66 @@
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+lazy_static! {
+    static ref ALIASES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+}
+
+pub fn get(name: &str) -> Option<String> {
+    ALIASES.read().unwrap().get(name).cloned()
+}
+
+pub fn set(name: &str, value: &str) {
+    ALIASES
+        .write()
+        .unwrap()
+        .insert(name.to_string(), value.to_string());
+}
+
+/// 删除别名，返回它之前是否存在
+pub fn remove(name: &str) -> bool {
+    ALIASES.write().unwrap().remove(name).is_some()
+}
+
+pub fn clear() {
+    ALIASES.write().unwrap().clear();
+}
+
+/// 按名称排序的全部别名
+pub fn all() -> Vec<(String, String)> {
+    ALIASES
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+/// 别名名称不能含有空白、引号、`=`、`/` 以及 shell 元字符
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.chars().any(|c| {
+            c.is_whitespace()
+                || matches!(
+                    c,
+                    '=' | '/'
+                        | '\''
+                        | '"'
+                        | '\\'
+                        | '$'
+                        | '`'
+                        | '|'
+                        | '&'
+                        | ';'
+                        | '<'
+                        | '>'
+                        | '('
+                        | ')'
+                )
+        })
+}
+
+/// 以单引号引用，`'` 写作 `'\''`，输出可以被 shell 重新读入
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
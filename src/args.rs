@@ -0,0 +1,112 @@
+use crate::println_error;
+
+/// 命令行参数：`sh-rs [-c command [name [args ...]]] [script [args ...]]`
+#[derive(Debug, Default)]
+pub struct Options {
+    /// `-c` 指定的命令字符串
+    pub command: Option<String>,
+    /// 要执行的脚本文件
+    pub script: Option<String>,
+    /// `$0` 的值，未指定时沿用启动时的程序名
+    pub arg0: Option<String>,
+    /// `$1` 起的位置参数
+    pub args: Vec<String>,
+    /// 登录 shell：`-l`、`--login` 或程序名以 `-` 开头
+    pub login: bool,
+    /// 不读取 /etc/profile 与 ~/.profile
+    pub no_profile: bool,
+    /// 不读取交互式启动文件与 `$ENV`
+    pub no_rc: bool,
+    /// 用指定文件代替默认的交互式启动文件
+    pub rc_file: Option<String>,
+}
+
+const USAGE: &str = "usage: sh-rs [-l|--login] [--noprofile] [--norc] [--rcfile file] \
+                     [-c command [name [args ...]]] [script [args ...]]";
+
+impl Options {
+    pub fn parse(mut argv: impl Iterator<Item = String>) -> Options {
+        let mut options = Options::default();
+        while let Some(arg) = argv.next() {
+            match arg.as_str() {
+                "-c" => match argv.next() {
+                    Some(command) => options.command = Some(command),
+                    None => usage_error("-c: option requires an argument"),
+                },
+                "-l" | "--login" => options.login = true,
+                "--noprofile" => options.no_profile = true,
+                "--norc" => options.no_rc = true,
+                "--rcfile" | "--init-file" => match argv.next() {
+                    Some(file) => options.rc_file = Some(file),
+                    None => usage_error(&format!("{}: option requires an argument", arg)),
+                },
+                "--" => break,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                "-V" | "--version" => {
+                    println!("sh-rs {}", env!("CARGO_PKG_VERSION"));
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    usage_error(&format!("{}: invalid option", arg))
+                }
+                _ => {
+                    options.positional(arg, argv);
+                    return options;
+                }
+            }
+        }
+        if let Some(first) = argv.next() {
+            options.positional(first, argv);
+        }
+        options
+    }
+
+    /// 第一个非选项参数：`-c` 模式下为 `$0`，否则为脚本路径
+    fn positional(&mut self, first: String, rest: impl Iterator<Item = String>) {
+        if self.command.is_none() {
+            self.script = Some(first.clone());
+        }
+        self.arg0 = Some(first);
+        self.args = rest.collect();
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    println_error!("sh-rs: {}", message);
+    println_error!("{}", USAGE);
+    std::process::exit(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Options {
+        Options::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_script_and_command() {
+        let options = parse(&["script.sh", "-x", "b"]);
+        assert_eq!(options.script.as_deref(), Some("script.sh"));
+        assert_eq!(options.arg0.as_deref(), Some("script.sh"));
+        assert_eq!(options.args, vec!["-x", "b"]);
+
+        let options = parse(&["-c", "echo $0", "name", "a"]);
+        assert_eq!(options.command.as_deref(), Some("echo $0"));
+        assert_eq!(options.script, None);
+        assert_eq!(options.arg0.as_deref(), Some("name"));
+        assert_eq!(options.args, vec!["a"]);
+
+        let options = parse(&[]);
+        assert!(options.command.is_none() && options.script.is_none());
+
+        let options = parse(&["--login", "--norc", "--rcfile", "rc", "-c", "true"]);
+        assert!(options.login && options.no_rc && !options.no_profile);
+        assert_eq!(options.rc_file.as_deref(), Some("rc"));
+        assert_eq!(options.command.as_deref(), Some("true"));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{abbr, alias, println_error};
+
+const USAGE: &str =
+    "abbr: usage: abbr [-a] name expansion... | abbr -e name... | abbr -q name... | abbr [-s | -l]";
+
+/// `abbr`，与 fish 相同：`-a` 添加、`-e` 删除、`-q` 查询、`-l` 列出名称、`-s` 以可重新读入的格式输出
+pub(crate) fn abbr(args: &[String]) -> i32 {
+    let mut mode = None;
+    let mut rest = args;
+    while let Some((arg, tail)) = rest.split_first() {
+        let flag = match arg.as_str() {
+            "--" => {
+                rest = tail;
+                break;
+            }
+            "-a" | "--add" => 'a',
+            "-e" | "--erase" => 'e',
+            "-q" | "--query" => 'q',
+            "-l" | "--list" => 'l',
+            "-s" | "--show" => 's',
+            option if option.starts_with('-') && option.len() > 1 => {
+                println_error!("abbr: {}: invalid option", option);
+                println_error!("{}", USAGE);
+                return 2;
+            }
+            _ => break,
+        };
+        if mode.is_some_and(|mode| mode != flag) {
+            println_error!("abbr: conflicting options");
+            return 2;
+        }
+        mode = Some(flag);
+        rest = tail;
+    }
+
+    let mode = mode.unwrap_or(if rest.is_empty() { 's' } else { 'a' });
+    match mode {
+        'a' => {
+            let Some((name, words)) = rest.split_first().filter(|(_, words)| !words.is_empty())
+            else {
+                println_error!("{}", USAGE);
+                return 2;
+            };
+            if !abbr::is_valid_name(name) {
+                println_error!("abbr: `{}': invalid abbreviation name", name);
+                return 1;
+            }
+            abbr::set(name, &words.join(" "));
+            0
+        }
+        'e' => {
+            let mut status = 0;
+            for name in rest {
+                if !abbr::remove(name) {
+                    println_error!("abbr: {}: not found", name);
+                    status = 1;
+                }
+            }
+            status
+        }
+        'q' => {
+            if rest.iter().any(|name| abbr::get(name).is_some()) {
+                0
+            } else {
+                1
+            }
+        }
+        'l' => {
+            for (name, _) in abbr::all() {
+                println!("{}", name);
+            }
+            0
+        }
+        _ => {
+            for (name, expansion) in abbr::all() {
+                println!("abbr -a -- {} {}", name, alias::quote(&expansion));
+            }
+            0
+        }
+    }
+}
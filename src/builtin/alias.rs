@@ -0,0 +1,54 @@
+use crate::{alias, println_error};
+
+/// `alias [-p] [name[=value] ...]`，输出格式可以被 `source` 重新读入
+pub(crate) fn alias(args: &[String]) -> i32 {
+    let args: Vec<&String> = args.iter().filter(|arg| arg.as_str() != "-p").collect();
+    if args.is_empty() {
+        for (name, value) in alias::all() {
+            println!("alias {}={}", name, alias::quote(&value));
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) => {
+                if !alias::is_valid_name(name) {
+                    println_error!("alias: `{}': invalid alias name", name);
+                    status = 1;
+                    continue;
+                }
+                alias::set(name, value);
+            }
+            None => match alias::get(arg) {
+                Some(value) => println!("alias {}={}", arg, alias::quote(&value)),
+                None => {
+                    println_error!("alias: {}: not found", arg);
+                    status = 1;
+                }
+            },
+        }
+    }
+    status
+}
+
+/// `unalias [-a] name ...`
+pub(crate) fn unalias(args: &[String]) -> i32 {
+    if args.is_empty() {
+        println_error!("unalias: usage: unalias [-a] name [name ...]");
+        return 2;
+    }
+    if args.iter().any(|arg| arg == "-a") {
+        alias::clear();
+        return 0;
+    }
+    let mut status = 0;
+    for name in args {
+        if !alias::remove(name) {
+            println_error!("unalias: {}: not found", name);
+            status = 1;
+        }
+    }
+    status
+}
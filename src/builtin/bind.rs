@@ -0,0 +1,217 @@
+use super::split_options;
+use crate::input::keymap::{self, ACTIONS, Action, Binding, Key, Keymap};
+use crate::{options, println_error};
+
+const USAGE: &str = "bind: usage: bind [-lpPX] [-m keymap] [-f filename] [-q name] [-u name] [-r keyseq] [-x keyseq:shell-command] [keyseq:function-name]";
+
+/// 需要参数的选项
+const WITH_VALUE: &str = "fmqrux";
+
+/// `"keyseq": value` 或 `keyseq: value`，返回按键序列与冒号之后的部分
+fn split_binding(line: &str) -> Result<(Vec<Key>, &str), String> {
+    let line = line.trim();
+    let (keyseq, rest) = match line.strip_prefix('"') {
+        Some(quoted) => {
+            let mut escaped = false;
+            let end = quoted
+                .char_indices()
+                .find(|&(_, c)| {
+                    let close = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    close
+                })
+                .map(|(i, _)| i)
+                .ok_or_else(|| format!("{}: missing closing quote", line))?;
+            (&quoted[..end], &quoted[end + 1..])
+        }
+        None => match line.find(':') {
+            Some(i) => (line[..i].trim_end(), &line[i..]),
+            None => (line, ""),
+        },
+    };
+    let Some(value) = rest.trim_start().strip_prefix(':') else {
+        return Err(format!("{}: no colon after key sequence", line));
+    };
+    Ok((keymap::parse_keyseq(keyseq)?, value.trim()))
+}
+
+/// 一行 readline 配置：`"keyseq": function-name` 或 `set editing-mode vi`
+fn apply(keymap: Keymap, line: &str) -> Result<(), String> {
+    if let Some(setting) = line.trim().strip_prefix("set ") {
+        let words: Vec<&str> = setting.split_whitespace().collect();
+        return match words[..] {
+            ["editing-mode", mode @ ("emacs" | "vi")] => options::set(mode, true),
+            _ => Err(format!("{}: unsupported readline variable", setting.trim())),
+        };
+    }
+    let (keys, name) = split_binding(line)?;
+    // 未绑定的普通字符就是 self-insert
+    if name == "self-insert" {
+        keymap::unbind(keymap, &keys);
+        return Ok(());
+    }
+    let action =
+        Action::from_name(name).ok_or_else(|| format!("`{}': unknown function name", name))?;
+    keymap::bind(keymap, keys, Binding::Action(action));
+    Ok(())
+}
+
+/// 绑定到 `action` 的全部按键序列
+fn keys_for(keymap: Keymap, action: Action) -> Vec<String> {
+    keymap::bindings(keymap)
+        .into_iter()
+        .filter(|(_, binding)| *binding == Binding::Action(action))
+        .map(|(keys, _)| format!("\"{}\"", keymap::keyseq_to_string(&keys)))
+        .collect()
+}
+
+/// 执行一个选项，返回退出状态
+fn run_option(keymap: Keymap, flag: char, value: Option<&str>) -> i32 {
+    match (flag, value) {
+        ('l', _) => {
+            for (name, _) in ACTIONS {
+                println!("{}", name);
+            }
+        }
+        ('p', _) => {
+            for (name, action) in ACTIONS {
+                let keys = keys_for(keymap, *action);
+                if keys.is_empty() {
+                    println!("# {} (not bound)", name);
+                }
+                for keyseq in keys {
+                    println!("{}: {}", keyseq, name);
+                }
+            }
+        }
+        ('P', _) => {
+            for (name, action) in ACTIONS {
+                let keys = keys_for(keymap, *action);
+                if keys.is_empty() {
+                    println!("{} is not bound to any keys", name);
+                } else {
+                    println!("{} can be found on {}.", name, keys.join(", "));
+                }
+            }
+        }
+        ('X', _) => {
+            for (keys, binding) in keymap::bindings(keymap) {
+                if let Binding::Command(command) = binding {
+                    println!("\"{}\": \"{}\"", keymap::keyseq_to_string(&keys), command);
+                }
+            }
+        }
+        ('q' | 'u', Some(name)) => {
+            let Some(action) = Action::from_name(name) else {
+                println_error!("bind: `{}': unknown function name", name);
+                return 1;
+            };
+            if flag == 'u' {
+                keymap::unbind_action(keymap, action);
+                return 0;
+            }
+            let keys = keys_for(keymap, action);
+            if keys.is_empty() {
+                println!("{} is not bound to any keys.", name);
+                return 1;
+            }
+            println!("{} can be invoked via {}.", name, keys.join(", "));
+        }
+        ('r', Some(keyseq)) => match keymap::parse_keyseq(keyseq) {
+            Ok(keys) => {
+                keymap::unbind(keymap, &keys);
+            }
+            Err(e) => {
+                println_error!("bind: {}", e);
+                return 1;
+            }
+        },
+        ('x', Some(line)) => match split_binding(line) {
+            Ok((keys, command)) => {
+                let command = command
+                    .strip_prefix('"')
+                    .and_then(|command| command.strip_suffix('"'))
+                    .unwrap_or(command);
+                keymap::bind(keymap, keys, Binding::Command(command.to_string()));
+            }
+            Err(e) => {
+                println_error!("bind: {}", e);
+                return 1;
+            }
+        },
+        ('f', Some(path)) => {
+            let contents = match std::fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    println_error!("bind: {}: {}", path, e);
+                    return 1;
+                }
+            };
+            let mut status = 0;
+            for (number, line) in contents.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                if let Err(e) = apply(keymap, line) {
+                    println_error!("bind: {}: line {}: {}", path, number + 1, e);
+                    status = 1;
+                }
+            }
+            return status;
+        }
+        _ => {}
+    }
+    0
+}
+
+/// `bind`：查看与修改行编辑器的按键绑定，选项与 bash 相同
+pub(crate) fn bind(args: &[String]) -> i32 {
+    let mut keymap = Keymap::current();
+    let Some((parsed, rest)) = split_options("bind", args, "lpPX", WITH_VALUE) else {
+        println_error!("{}", USAGE);
+        return 2;
+    };
+    let mut options = Vec::new();
+    for (flag, value) in parsed {
+        match (flag, value) {
+            ('m', Some(value)) => match Keymap::from_name(value) {
+                Some(named) => keymap = named,
+                None => {
+                    println_error!("bind: `{}': invalid keymap name", value);
+                    return 1;
+                }
+            },
+            _ => options.push((flag, value)),
+        }
+    }
+
+    let mut status = 0;
+    for (flag, value) in options {
+        status |= run_option(keymap, flag, value);
+    }
+    for line in rest {
+        if let Err(e) = apply(keymap, line) {
+            println_error!("bind: {}", e);
+            status = 1;
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_binding() {
+        let (keys, value) = split_binding(r#""\C-x\"": "echo hi""#).unwrap();
+        assert_eq!(keymap::keyseq_to_string(&keys), r#"\C-x\""#);
+        assert_eq!(value, "\"echo hi\"");
+        let (keys, value) = split_binding(r"\ef : forward-word").unwrap();
+        assert_eq!(keymap::keyseq_to_string(&keys), r"\ef");
+        assert_eq!(value, "forward-word");
+        assert!(split_binding(r#""\C-a" kill-line"#).is_err());
+        assert!(split_binding(r#""\C-a: kill-line"#).is_err());
+    }
+}
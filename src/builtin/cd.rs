@@ -0,0 +1,201 @@
+use crate::{frecency, println_error, var};
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+/// 启动时校验继承来的 `PWD`，不指向当前目录时改用物理路径
+pub(crate) fn init_pwd() {
+    let pwd = logical_pwd();
+    var::set_exported("PWD", &pwd.display().to_string());
+}
+
+/// 逻辑当前目录：`PWD` 是指向当前目录的绝对路径时使用它，否则使用物理路径
+pub(crate) fn logical_pwd() -> PathBuf {
+    let physical = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+    match var::get("PWD").map(PathBuf::from) {
+        Some(pwd) if pwd.is_absolute() && same_file(&pwd, &physical) => pwd,
+        _ => physical,
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// 按逻辑路径规范化：去掉 `.`，`..` 删除前一个组成部分，不解析符号链接
+pub(crate) fn normalize_logical(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(name) => normalized.push(name),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    normalized
+}
+
+/// `cd` 切换目录：切换后记入目录访问数据库（仅限交互输入的命令）
+pub(crate) fn change_dir(dir: &str, physical: bool) -> Result<PathBuf, String> {
+    let target = switch_dir(dir, physical)?;
+    frecency::add(&target);
+    Ok(target)
+}
+
+/// 切换目录并更新 `PWD` 与 `OLDPWD`，返回新的逻辑路径；`pushd`、`popd` 与 `z` 使用，不计入访问次数
+pub(crate) fn switch_dir(dir: &str, physical: bool) -> Result<PathBuf, String> {
+    let old = logical_pwd();
+    let target = if physical {
+        env::set_current_dir(dir).map_err(|e| format!("{}: {}", dir, error_message(&e)))?;
+        env::current_dir().map_err(|e| e.to_string())?
+    } else {
+        let target = normalize_logical(&old.join(dir));
+        env::set_current_dir(&target).map_err(|e| format!("{}: {}", dir, error_message(&e)))?;
+        target
+    };
+    var::set_exported("OLDPWD", &old.display().to_string());
+    var::set_exported("PWD", &target.display().to_string());
+    Ok(target)
+}
+
+/// 去掉 io::Error 末尾的 `(os error N)`
+fn error_message(e: &std::io::Error) -> String {
+    let message = e.to_string();
+    match message.find(" (os error") {
+        Some(end) => message[..end].to_string(),
+        None => message,
+    }
+}
+
+/// 不以 `/`、`.`、`..` 开头的目录在 `CDPATH` 中查找，返回目录与是否需要输出新路径
+fn search_cdpath(dir: &str) -> (String, bool) {
+    let first = Path::new(dir).components().next();
+    if !matches!(first, Some(Component::Normal(_))) {
+        return (dir.to_string(), false);
+    }
+    let Some(cdpath) = var::get("CDPATH") else {
+        return (dir.to_string(), false);
+    };
+    for entry in cdpath.split(':') {
+        let candidate = if entry.is_empty() {
+            PathBuf::from(".").join(dir)
+        } else {
+            Path::new(entry).join(dir)
+        };
+        if candidate.is_dir() {
+            return (candidate.display().to_string(), !entry.is_empty());
+        }
+    }
+    (dir.to_string(), false)
+}
+
+/// 解析 `-L`/`-P` 选项，返回是否使用物理路径与剩余参数
+fn parse_options<'a>(name: &str, args: &'a [String]) -> Result<(bool, &'a [String]), ()> {
+    let mut physical = false;
+    let mut rest = args;
+    while let Some((arg, tail)) = rest.split_first() {
+        if arg == "--" {
+            return Ok((physical, tail));
+        }
+        let Some(flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+            break;
+        };
+        for flag in flags.chars() {
+            match flag {
+                'L' => physical = false,
+                'P' => physical = true,
+                _ => {
+                    println_error!("{}: -{}: invalid option", name, flag);
+                    let operand = if name == "cd" { " [dir]" } else { "" };
+                    println_error!("{}: usage: {} [-L|-P]{}", name, name, operand);
+                    return Err(());
+                }
+            }
+        }
+        rest = tail;
+    }
+    Ok((physical, rest))
+}
+
+/// `cd [-L|-P] [dir]`，`cd -` 回到 `OLDPWD` 并输出新路径
+pub(crate) fn cd(args: &[String]) -> i32 {
+    let Ok((physical, args)) = parse_options("cd", args) else {
+        return 2;
+    };
+    if args.len() > 1 {
+        println_error!("cd: too many arguments");
+        return 1;
+    }
+    let (dir, print) = match args.first().map(String::as_str) {
+        None => match var::get("HOME") {
+            Some(home) => (home, false),
+            None => {
+                println_error!("cd: HOME not set");
+                return 1;
+            }
+        },
+        Some("-") => match var::get("OLDPWD") {
+            Some(old) => (old, true),
+            None => {
+                println_error!("cd: OLDPWD not set");
+                return 1;
+            }
+        },
+        Some(dir) => search_cdpath(dir),
+    };
+    match change_dir(&dir, physical) {
+        Ok(target) => {
+            if print {
+                println!("{}", target.display());
+            }
+            0
+        }
+        Err(e) => {
+            println_error!("cd: {}", e);
+            1
+        }
+    }
+}
+
+/// `pwd [-L|-P]`
+pub(crate) fn pwd(args: &[String]) -> i32 {
+    let Ok((physical, _)) = parse_options("pwd", args) else {
+        return 2;
+    };
+    let dir = if physical {
+        match env::current_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                println_error!("pwd: {}", e);
+                return 1;
+            }
+        }
+    } else {
+        logical_pwd()
+    };
+    println!("{}", dir.display());
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_logical() {
+        for (input, expected) in [
+            ("/usr/local/../bin", "/usr/bin"),
+            ("/a/./b/", "/a/b"),
+            ("/..", "/"),
+            ("/a/b/../../..", "/"),
+            ("/link/..", "/"),
+        ] {
+            assert_eq!(normalize_logical(Path::new(input)), Path::new(expected));
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use super::split_flags;
+use crate::resolve::{self, Resolution};
+use crate::{alias, println_error};
+
+fn kind(resolution: &Resolution) -> &'static str {
+    match resolution {
+        Resolution::Alias(_) => "alias",
+        Resolution::Keyword => "keyword",
+        Resolution::Function => "function",
+        Resolution::Builtin => "builtin",
+        Resolution::File { .. } => "file",
+    }
+}
+
+/// `type` 与 `command -V` 的描述
+fn describe(name: &str, resolution: &Resolution) -> String {
+    match resolution {
+        Resolution::Alias(value) => format!("{} is aliased to `{}'", name, value),
+        Resolution::Keyword => format!("{} is a shell keyword", name),
+        Resolution::Function => format!("{} is a function", name),
+        Resolution::Builtin => format!("{} is a shell builtin", name),
+        Resolution::File { path, hashed: true } => {
+            format!("{} is hashed ({})", name, path.display())
+        }
+        Resolution::File { path, .. } => format!("{} is {}", name, path.display()),
+    }
+}
+
+/// `type [-afptP] name ...`
+pub(crate) fn type_(args: &[String]) -> i32 {
+    let Some((flags, names)) = split_flags("type", args, "afptP") else {
+        println_error!("type: usage: type [-afptP] name [name ...]");
+        return 2;
+    };
+    let all = flags.contains('a');
+    let mut status = 0;
+    for name in names {
+        let mut found: Vec<Resolution> = if flags.contains('P') {
+            resolve::search_path(name)
+                .map(|path| Resolution::File {
+                    path,
+                    hashed: false,
+                })
+                .into_iter()
+                .collect()
+        } else {
+            resolve::resolve_all(name)
+        };
+        if flags.contains('f') {
+            found.retain(|resolution| *resolution != Resolution::Function);
+        }
+        if !all {
+            found.truncate(1);
+        }
+        if found.is_empty() {
+            if !flags.contains('t') && !flags.contains('p') && !flags.contains('P') {
+                println_error!("type: {}: not found", name);
+            }
+            status = 1;
+            continue;
+        }
+        for resolution in &found {
+            if flags.contains('t') {
+                println!("{}", kind(resolution));
+            } else if flags.contains('p') || flags.contains('P') {
+                if let Resolution::File { path, .. } = resolution {
+                    println!("{}", path.display());
+                }
+            } else {
+                println!("{}", describe(name, resolution));
+            }
+        }
+    }
+    status
+}
+
+/// `command -v name` 或 `command -V name`；带参数执行命令的形式由执行器处理
+pub(crate) fn command(args: &[String]) -> i32 {
+    let Some((flags, names)) = split_flags("command", args, "pvV") else {
+        println_error!("command: usage: command [-pVv] command [arg ...]");
+        return 2;
+    };
+    let verbose = flags.contains('V');
+    if !verbose && !flags.contains('v') {
+        return 0;
+    }
+    let mut status = 0;
+    for name in names {
+        let Some(resolution) = resolve::resolve(name) else {
+            if verbose {
+                println_error!("command: {}: not found", name);
+            }
+            status = 1;
+            continue;
+        };
+        if verbose {
+            println!("{}", describe(name, &resolution));
+            continue;
+        }
+        match resolution {
+            Resolution::Alias(value) => println!("alias {}={}", name, alias::quote(&value)),
+            Resolution::File { path, .. } => println!("{}", path.display()),
+            _ => println!("{}", name),
+        }
+    }
+    status
+}
+
+/// `hash [-r] [-t name ...] [name ...]`
+pub(crate) fn hash(args: &[String]) -> i32 {
+    let Some((flags, names)) = split_flags("hash", args, "rt") else {
+        println_error!("hash: usage: hash [-r] [-t name ...] [name ...]");
+        return 2;
+    };
+    if flags.contains('r') {
+        resolve::clear_hash();
+    }
+    if flags.contains('t') {
+        let hashed = resolve::hashed();
+        let mut status = 0;
+        for name in names {
+            match hashed.iter().find(|(hashed, _, _)| hashed == name) {
+                Some((_, path, _)) if names.len() == 1 => println!("{}", path.display()),
+                Some((_, path, _)) => println!("{}\t{}", name, path.display()),
+                None => {
+                    println_error!("hash: {}: not found", name);
+                    status = 1;
+                }
+            }
+        }
+        return status;
+    }
+    if names.is_empty() {
+        if flags.contains('r') {
+            return 0;
+        }
+        let hashed = resolve::hashed();
+        if hashed.is_empty() {
+            println!("hash: hash table empty");
+        } else {
+            println!("hits\tcommand");
+            for (_, path, hits) in hashed {
+                println!("{:4}\t{}", hits, path.display());
+            }
+        }
+        return 0;
+    }
+    let mut status = 0;
+    for name in names {
+        // 函数与内置命令不需要缓存
+        if matches!(
+            resolve::resolve(name),
+            Some(Resolution::Function | Resolution::Builtin)
+        ) {
+            continue;
+        }
+        if resolve::hash(name).is_none() {
+            println_error!("hash: {}: not found", name);
+            status = 1;
+        }
+    }
+    status
+}
+
+/// `which [-a] name ...`，除可执行文件外也报告别名、函数与内置命令
+pub(crate) fn which(args: &[String]) -> i32 {
+    let Some((flags, names)) = split_flags("which", args, "a") else {
+        println_error!("which: usage: which [-a] name [name ...]");
+        return 2;
+    };
+    let mut status = 0;
+    for name in names {
+        let mut found = resolve::resolve_all(name);
+        if !flags.contains('a') {
+            found.truncate(1);
+        }
+        if found.is_empty() {
+            println_error!("{} not found", name);
+            status = 1;
+        }
+        for resolution in found {
+            match resolution {
+                Resolution::Alias(value) => println!("{}: aliased to {}", name, value),
+                Resolution::Keyword => println!("{}: shell reserved word", name),
+                Resolution::Function => println!("{}: shell function", name),
+                Resolution::Builtin => println!("{}: shell built-in command", name),
+                Resolution::File { path, .. } => println!("{}", path.display()),
+            }
+        }
+    }
+    status
+}
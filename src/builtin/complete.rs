@@ -0,0 +1,123 @@
+use super::split_options;
+use crate::complete::{self, ACTIONS, OPTIONS, Spec};
+use crate::println_error;
+
+const USAGE: &str = "complete: usage: complete [-abcdfv] [-pr] [-o option] [-A action] [-W wordlist] [-F function] [-X filterpat] [-P prefix] [-S suffix] [name ...]";
+
+/// 需要参数的选项
+const WITH_VALUE: &str = "oAWFXPS";
+
+/// `complete`：为命令注册补全规则；`-p` 或不带参数时列出规则，`-r` 删除规则
+pub(crate) fn complete(args: &[String]) -> i32 {
+    let mut spec = Spec::default();
+    let mut print = false;
+    let mut remove = false;
+    let allowed: String = "pr"
+        .chars()
+        .chain(ACTIONS.iter().filter_map(|(_, short)| *short))
+        .collect();
+    let Some((options, rest)) = split_options("complete", args, &allowed, WITH_VALUE) else {
+        println_error!("{}", USAGE);
+        return 2;
+    };
+    for (flag, value) in options {
+        match (flag, value) {
+            ('p', _) => print = true,
+            ('r', _) => remove = true,
+            (_, None) => {
+                if let Some((action, _)) = ACTIONS.iter().find(|(_, short)| *short == Some(flag)) {
+                    spec.actions.push(action);
+                }
+            }
+            ('o', Some(value)) => match OPTIONS.iter().find(|option| **option == value) {
+                Some(option) => spec.options.push(option),
+                None => {
+                    println_error!("complete: {}: invalid option name", value);
+                    return 2;
+                }
+            },
+            ('A', Some(value)) => match ACTIONS.iter().find(|(action, _)| *action == value) {
+                Some((action, _)) => spec.actions.push(action),
+                None => {
+                    println_error!("complete: {}: invalid action name", value);
+                    return 2;
+                }
+            },
+            ('W', Some(value)) => spec.words = Some(value.to_string()),
+            ('F', Some(value)) => spec.function = Some(value.to_string()),
+            ('X', Some(value)) => spec.filter = Some(value.to_string()),
+            ('P', Some(value)) => spec.prefix = value.to_string(),
+            (_, Some(value)) => spec.suffix = value.to_string(),
+        }
+    }
+
+    if remove {
+        if rest.is_empty() {
+            complete::clear();
+        }
+        let mut status = 0;
+        for name in rest {
+            if !complete::remove(name) {
+                println_error!("complete: {}: no completion specification", name);
+                status = 1;
+            }
+        }
+        return status;
+    }
+    if print || rest.is_empty() {
+        if rest.is_empty() {
+            for (name, spec) in complete::all() {
+                println!("{}", spec.to_command(&name));
+            }
+            return 0;
+        }
+        let mut status = 0;
+        for name in rest {
+            match complete::get(name) {
+                Some(spec) => println!("{}", spec.to_command(name)),
+                None => {
+                    println_error!("complete: {}: no completion specification", name);
+                    status = 1;
+                }
+            }
+        }
+        return status;
+    }
+    for name in rest {
+        complete::set(name, spec.clone());
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_complete_builtin() {
+        assert_eq!(
+            complete(&args(&["-o", "nospace", "-W", "start stop", "-d", "t_svc"])),
+            0
+        );
+        let spec = complete::get("t_svc").unwrap();
+        assert_eq!(spec.words.as_deref(), Some("start stop"));
+        assert_eq!(spec.actions, vec!["directory"]);
+        assert_eq!(
+            spec.to_command("t_svc"),
+            "complete -o nospace -d -W 'start stop' t_svc"
+        );
+        assert_eq!(complete(&args(&["-F_t_svc", "t_svc", "t_svc2"])), 0);
+        assert_eq!(
+            complete::get("t_svc2").unwrap().function.as_deref(),
+            Some("_t_svc")
+        );
+        assert_eq!(complete(&args(&["-r", "t_svc", "t_svc2"])), 0);
+        assert!(complete::get("t_svc").is_none());
+        assert_eq!(complete(&args(&["-o", "bogus", "x"])), 2);
+        assert_eq!(complete(&args(&["-A", "bogus", "x"])), 2);
+    }
+}
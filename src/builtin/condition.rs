@@ -0,0 +1,468 @@
+use crate::{println_error, var};
+use regex::Regex;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+/// POSIX `test` / `[`：0 为真，1 为假，2 为用法错误
+pub(crate) fn test(args: &[String]) -> i32 {
+    report("test", evaluate_posix(args))
+}
+
+/// `[[ ... ]]`：支持 `&&`、`||`、模式匹配与 `=~` 正则匹配
+pub(crate) fn extended_test(args: &[String]) -> i32 {
+    let mut parser = Parser::new(args, true);
+    report("[[", parser.parse())
+}
+
+fn report(name: &str, result: Result<bool, String>) -> i32 {
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            println_error!("{}: {}", name, e);
+            2
+        }
+    }
+}
+
+/// 按 POSIX 规定，参数不超过 4 个时依据参数个数决定解析方式
+fn evaluate_posix(args: &[String]) -> Result<bool, String> {
+    let a: Vec<&str> = args.iter().map(String::as_str).collect();
+    if a.len() > 4 {
+        return Parser::new(args, false).parse();
+    }
+    match a.as_slice() {
+        [] => Ok(false),
+        [s] => Ok(!s.is_empty()),
+        ["!", s] => Ok(s.is_empty()),
+        [op, operand] if is_unary_op(op) => unary(op, operand),
+        [_, _] => Err(format!("{}: unary operator expected", a[0])),
+        [l, op, r] if is_binary_op(op, false) => binary(l, op, r, false),
+        ["!", ..] => evaluate_posix(&args[1..]).map(|b| !b),
+        ["(", s, ")"] => Ok(!s.is_empty()),
+        ["(", _, _, ")"] => evaluate_posix(&args[1..3]),
+        _ => Parser::new(args, false).parse(),
+    }
+}
+
+fn is_unary_op(op: &str) -> bool {
+    matches!(
+        op,
+        "-e" | "-f"
+            | "-d"
+            | "-r"
+            | "-w"
+            | "-x"
+            | "-s"
+            | "-L"
+            | "-h"
+            | "-p"
+            | "-S"
+            | "-b"
+            | "-c"
+            | "-g"
+            | "-u"
+            | "-k"
+            | "-t"
+            | "-n"
+            | "-z"
+            | "-v"
+    )
+}
+
+fn is_binary_op(op: &str, extended: bool) -> bool {
+    match op {
+        "=" | "!=" | "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge" | "-nt" | "-ot" | "-ef" => true,
+        "==" | "<" | ">" => true,
+        "=~" => extended,
+        _ => false,
+    }
+}
+
+/// 递归下降解析器，`extended` 为真时按 `[[ ]]` 的语法解析
+struct Parser<'a> {
+    args: &'a [String],
+    pos: usize,
+    extended: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(args: &'a [String], extended: bool) -> Self {
+        Parser {
+            args,
+            pos: 0,
+            extended,
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.args.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let arg = self.peek();
+        self.pos += 1;
+        arg
+    }
+
+    fn parse(&mut self) -> Result<bool, String> {
+        if self.args.is_empty() {
+            return if self.extended {
+                Err("expression expected".to_string())
+            } else {
+                Ok(false)
+            };
+        }
+        let value = self.parse_or()?;
+        match self.peek() {
+            None => Ok(value),
+            Some(extra) => Err(format!("{}: unexpected argument", extra)),
+        }
+    }
+
+    fn or_op(&self) -> &'static str {
+        if self.extended { "||" } else { "-o" }
+    }
+
+    fn and_op(&self) -> &'static str {
+        if self.extended { "&&" } else { "-a" }
+    }
+
+    fn parse_or(&mut self) -> Result<bool, String> {
+        let mut value = self.parse_and()?;
+        while self.peek() == Some(self.or_op()) {
+            self.next();
+            // 两侧都需要解析以消耗参数，不能短路
+            let rhs = self.parse_and()?;
+            value = value || rhs;
+        }
+        Ok(value)
+    }
+
+    fn parse_and(&mut self) -> Result<bool, String> {
+        let mut value = self.parse_not()?;
+        while self.peek() == Some(self.and_op()) {
+            self.next();
+            let rhs = self.parse_not()?;
+            value = value && rhs;
+        }
+        Ok(value)
+    }
+
+    fn parse_not(&mut self) -> Result<bool, String> {
+        if self.peek() == Some("!") && self.args.len() > self.pos + 1 {
+            self.next();
+            return self.parse_not().map(|b| !b);
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<bool, String> {
+        let Some(arg) = self.next() else {
+            return Err("argument expected".to_string());
+        };
+
+        // 二元运算优先于括号与一元运算，例如 `[ "(" = "(" ]`
+        if let (Some(op), Some(rhs)) = (self.peek(), self.args.get(self.pos + 1))
+            && is_binary_op(op, self.extended)
+        {
+            self.pos += 2;
+            return binary(arg, op, rhs, self.extended);
+        }
+
+        if arg == "(" {
+            let value = self.parse_or()?;
+            return match self.next() {
+                Some(")") => Ok(value),
+                _ => Err("`)' expected".to_string()),
+            };
+        }
+
+        if is_unary_op(arg)
+            && let Some(operand) = self.peek()
+            && operand != self.and_op()
+            && operand != self.or_op()
+            && operand != ")"
+        {
+            self.next();
+            return unary(arg, operand);
+        }
+
+        Ok(!arg.is_empty())
+    }
+}
+
+fn unary(op: &str, operand: &str) -> Result<bool, String> {
+    let result = match op {
+        "-n" => !operand.is_empty(),
+        "-z" => operand.is_empty(),
+        "-v" => var::is_set(operand),
+        "-t" => {
+            let fd: i32 = operand
+                .parse()
+                .map_err(|_| format!("{}: integer expression expected", operand))?;
+            unsafe { libc::isatty(fd) == 1 }
+        }
+        "-L" | "-h" => fs::symlink_metadata(operand)
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false),
+        "-r" => access(operand, libc::R_OK),
+        "-w" => access(operand, libc::W_OK),
+        "-x" => access(operand, libc::X_OK),
+        _ => {
+            let Ok(meta) = fs::metadata(operand) else {
+                return Ok(false);
+            };
+            let file_type = meta.file_type();
+            match op {
+                "-e" => true,
+                "-f" => file_type.is_file(),
+                "-d" => file_type.is_dir(),
+                "-s" => meta.len() > 0,
+                "-p" => file_type.is_fifo(),
+                "-S" => file_type.is_socket(),
+                "-b" => file_type.is_block_device(),
+                "-c" => file_type.is_char_device(),
+                "-g" => meta.mode() & libc::S_ISGID != 0,
+                "-u" => meta.mode() & libc::S_ISUID != 0,
+                "-k" => meta.mode() & libc::S_ISVTX != 0,
+                _ => return Err(format!("{}: unary operator expected", op)),
+            }
+        }
+    };
+    Ok(result)
+}
+
+fn access(path: &str, mode: i32) -> bool {
+    match CString::new(path) {
+        Ok(c_path) => unsafe { libc::access(c_path.as_ptr(), mode) == 0 },
+        Err(_) => false,
+    }
+}
+
+fn binary(lhs: &str, op: &str, rhs: &str, extended: bool) -> Result<bool, String> {
+    let result = match op {
+        "=" | "==" if extended => glob_match(rhs, lhs),
+        "!=" if extended => !glob_match(rhs, lhs),
+        "=" | "==" => lhs == rhs,
+        "!=" => lhs != rhs,
+        "<" => lhs < rhs,
+        ">" => lhs > rhs,
+        "=~" => regex_match(lhs, rhs)?,
+        "-eq" => integer(lhs)? == integer(rhs)?,
+        "-ne" => integer(lhs)? != integer(rhs)?,
+        "-lt" => integer(lhs)? < integer(rhs)?,
+        "-le" => integer(lhs)? <= integer(rhs)?,
+        "-gt" => integer(lhs)? > integer(rhs)?,
+        "-ge" => integer(lhs)? >= integer(rhs)?,
+        "-nt" | "-ot" => {
+            let modified = |path: &str| fs::metadata(path).and_then(|m| m.modified()).ok();
+            match (modified(lhs), modified(rhs)) {
+                (Some(l), Some(r)) if op == "-nt" => l > r,
+                (Some(l), Some(r)) => l < r,
+                (Some(_), None) => op == "-nt",
+                (None, Some(_)) => op == "-ot",
+                (None, None) => false,
+            }
+        }
+        "-ef" => match (fs::metadata(lhs), fs::metadata(rhs)) {
+            (Ok(l), Ok(r)) => l.dev() == r.dev() && l.ino() == r.ino(),
+            _ => false,
+        },
+        _ => return Err(format!("{}: binary operator expected", op)),
+    };
+    Ok(result)
+}
+
+fn integer(s: &str) -> Result<i64, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("{}: integer expression expected", s))
+}
+
+/// 正则匹配的整体与各捕获组依次写入数组 `BASH_REMATCH`
+fn regex_match(text: &str, pattern: &str) -> Result<bool, String> {
+    let re = Regex::new(pattern).map_err(|e| format!("invalid regex `{}': {}", pattern, e))?;
+    let groups: Vec<String> = match re.captures(text) {
+        Some(caps) => caps
+            .iter()
+            .map(|m| m.map_or("", |m| m.as_str()).to_string())
+            .collect(),
+        None => Vec::new(),
+    };
+    let matched = !groups.is_empty();
+    var::set_array("BASH_REMATCH", &groups, false)?;
+    Ok(matched)
+}
+
+/// shell 通配符匹配，支持 `*`、`?`、`[...]`、`[!...]` 以及反斜杠转义
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一次 `*` 的位置，用于回溯
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' => {
+                    star = Some((p, t));
+                    p += 1;
+                    continue;
+                }
+                '?' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                '[' => {
+                    if let Some((matched, next)) = match_class(&pattern, p, text[t]) {
+                        if matched {
+                            p = next;
+                            t += 1;
+                            continue;
+                        }
+                    } else if text[t] == '[' {
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                }
+                '\\' if p + 1 < pattern.len() => {
+                    if pattern[p + 1] == text[t] {
+                        p += 2;
+                        t += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if c == text[t] {
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+        match star {
+            Some((sp, st)) => {
+                p = sp + 1;
+                t = st + 1;
+                star = Some((sp, st + 1));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// 匹配 `[...]` 字符类，返回是否匹配以及字符类之后的位置；字符类未闭合时返回 `None`
+fn match_class(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        let lo = pattern[i];
+        if lo == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            let hi = pattern[i + 2];
+            if lo <= c && c <= hi {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if lo == c {
+                matched = true;
+            }
+            i += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_posix_strings_and_integers() {
+        assert_eq!(test(&args("abc")), 0);
+        assert_eq!(test(&[]), 1);
+        assert_eq!(test(&args("-n")), 0);
+        assert_eq!(test(&args("a = a")), 0);
+        assert_eq!(test(&args("a != a")), 1);
+        assert_eq!(test(&args("3 -lt 10")), 0);
+        assert_eq!(test(&args("! 3 -gt 10")), 0);
+        assert_eq!(test(&args("x -eq 1")), 2);
+    }
+
+    #[test]
+    fn test_posix_connectives_and_parens() {
+        assert_eq!(test(&args("a = a -a b = c")), 1);
+        assert_eq!(test(&args("a = a -o b = c")), 0);
+        assert_eq!(test(&args("( a = b -o 1 -eq 1 ) -a -n x")), 0);
+        assert_eq!(test(&args("! ( a = a )")), 1);
+    }
+
+    #[test]
+    fn test_file_tests() {
+        assert_eq!(test(&args("-d /")), 0);
+        assert_eq!(test(&args("-f /")), 1);
+        assert_eq!(test(&args("-e /definitely/not/here")), 1);
+    }
+
+    #[test]
+    fn test_extended_pattern_and_regex() {
+        assert_eq!(extended_test(&args("foobar == foo*")), 0);
+        assert_eq!(extended_test(&args("foobar != f?o[a-z]ar")), 1);
+        assert_eq!(extended_test(&args("b < a || -n x && 2 -ge 2")), 0);
+        assert_eq!(extended_test(&args("v1.2 =~ ^v([0-9]+)\\.")), 0);
+        assert_eq!(var::values("BASH_REMATCH"), vec!["v1.", "1"]);
+        assert_eq!(extended_test(&args("x =~ (")), 2);
+    }
+
+    #[test]
+    fn test_extended_quoted_pattern() {
+        // 经过词法分析：引号中的通配符按字面匹配，未加引号的才是模式
+        let cond = |line: &str| {
+            let words: Vec<String> = crate::token::tokenize(line)
+                .into_iter()
+                .filter_map(|token| match token {
+                    crate::token::Token::Word(word) => Some(word),
+                    _ => None,
+                })
+                .collect();
+            extended_test(&words[1..words.len() - 1])
+        };
+        assert_eq!(cond("[[ abc == \"a*\" ]]"), 1);
+        assert_eq!(cond("[[ abc == a* ]]"), 0);
+        assert_eq!(cond("[[ a* == \"a*\" ]]"), 0);
+        assert_eq!(cond("[[ ab != 'a'? ]]"), 1);
+        assert_eq!(cond("[[ a? == a\\? ]]"), 0);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "main.rc"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(glob_match("[!0-9]x", "ax"));
+        assert!(!glob_match("[!0-9]x", "1x"));
+        assert!(glob_match("\\*", "*"));
+        assert!(glob_match("[", "["));
+    }
+}
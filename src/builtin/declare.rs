@@ -0,0 +1,196 @@
+use crate::token::split_array_argument;
+use crate::{exec, println_error, var};
+
+/// `declare [-a|-A|-p] [name[=value] ...]`
+pub(crate) fn declare(args: &[String]) -> i32 {
+    let mut associative = false;
+    let mut indexed = false;
+    let mut print = false;
+    let mut names = Vec::new();
+
+    for arg in args {
+        match arg.strip_prefix('-') {
+            Some(flags) if !flags.is_empty() && names.is_empty() => {
+                for flag in flags.chars() {
+                    match flag {
+                        'a' => indexed = true,
+                        'A' => associative = true,
+                        'p' => print = true,
+                        _ => {
+                            println_error!("declare: -{}: invalid option", flag);
+                            println_error!("declare: usage: declare [-aAp] [name[=value] ...]");
+                            return 2;
+                        }
+                    }
+                }
+            }
+            _ => names.push(arg.as_str()),
+        }
+    }
+
+    if print || names.is_empty() {
+        let names = if names.is_empty() {
+            var::names()
+        } else {
+            names.iter().map(|name| name.to_string()).collect()
+        };
+        let mut status = 0;
+        for name in names {
+            match var::describe(&name) {
+                Some(line) => println!("{}", line),
+                None => {
+                    println_error!("declare: {}: not found", name);
+                    status = 1;
+                }
+            }
+        }
+        return status;
+    }
+
+    let mut status = 0;
+    for spec in names {
+        let (name, value) = match spec.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (spec, None),
+        };
+        if let Err(e) = define(name, value, indexed, associative) {
+            println_error!("declare: {}", e);
+            status = 1;
+        }
+    }
+    status
+}
+
+/// 定义变量：`name=(...)` 是词法分析得到的数组字面量；`declare -a x='(1 2)'`
+/// 这样引号中的括号与 bash 一样在声明为数组时按空白拆分
+fn define(name: &str, value: Option<&str>, indexed: bool, associative: bool) -> Result<(), String> {
+    let (name, append) = match name.strip_suffix('+') {
+        Some(name) if value.is_some() => (name, true),
+        _ => (name, false),
+    };
+    if !var::is_valid_name(name) {
+        return Err(format!("`{}': not a valid identifier", name));
+    }
+    if indexed || associative {
+        var::declare(name, associative)?;
+    }
+    match value {
+        Some(list) if let Some(elements) = split_array_argument(list) => {
+            var::set_array(name, &elements, append)
+        }
+        Some(list) if (indexed || associative) && list.starts_with('(') && list.ends_with(')') => {
+            let elements: Vec<String> = list[1..list.len() - 1]
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            var::set_array(name, &elements, append)
+        }
+        Some(value) if indexed || associative => var::set_element(name, "0", value, append),
+        Some(value) => {
+            var::set_scalar(name, value, append);
+            Ok(())
+        }
+        None if !indexed && !associative && !var::is_set(name) => {
+            var::set_scalar(name, "", false);
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// `unset [-f|-v] name ...`：默认删除变量，没有同名变量时删除函数；`name[index]` 删除数组的一个元素
+pub(crate) fn unset(args: &[String]) -> i32 {
+    let mut functions = false;
+    let mut variables = false;
+    let mut names = Vec::new();
+    for arg in args {
+        match arg.strip_prefix('-') {
+            Some(flags) if !flags.is_empty() && names.is_empty() => {
+                for flag in flags.chars() {
+                    match flag {
+                        'f' => functions = true,
+                        'v' => variables = true,
+                        _ => {
+                            println_error!("unset: -{}: invalid option", flag);
+                            println_error!("unset: usage: unset [-f] [-v] [name ...]");
+                            return 2;
+                        }
+                    }
+                }
+            }
+            _ => names.push(arg.as_str()),
+        }
+    }
+    if functions && variables {
+        println_error!("unset: cannot simultaneously unset a function and a variable");
+        return 1;
+    }
+
+    let mut status = 0;
+    for name in names {
+        if functions {
+            exec::remove_function(name);
+            continue;
+        }
+        let (base, index) = match name.find('[') {
+            Some(open) if name.ends_with(']') => {
+                (&name[..open], Some(&name[open + 1..name.len() - 1]))
+            }
+            _ => (name, None),
+        };
+        if !var::is_valid_name(base) {
+            println_error!("unset: `{}': not a valid identifier", name);
+            status = 1;
+            continue;
+        }
+        match index {
+            Some(index) => {
+                if let Err(e) = var::unset_element(base, index) {
+                    println_error!("unset: {}", e);
+                    status = 1;
+                }
+            }
+            None if !variables && !var::is_set(name) => {
+                exec::remove_function(name);
+            }
+            None => var::unset(name),
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_declare_array_literals() {
+        exec::execute_line("declare -A DECLARE_M=([a]=1 [b]=\"2 3\")")
+            .await
+            .unwrap();
+        assert_eq!(var::get_element("DECLARE_M", "a"), Some("1".to_string()));
+        assert_eq!(var::get_element("DECLARE_M", "b"), Some("2 3".to_string()));
+        assert_eq!(var::keys("DECLARE_M"), vec!["a", "b"]);
+
+        exec::execute_line("declare -a DECLARE_X=(1 2 3); declare DECLARE_X+=(4)")
+            .await
+            .unwrap();
+        assert_eq!(var::values("DECLARE_X"), vec!["1", "2", "3", "4"]);
+
+        // 引号中的括号不声明为数组时只是字符串
+        exec::execute_line("declare DECLARE_S='(1 2)'")
+            .await
+            .unwrap();
+        assert_eq!(var::values("DECLARE_S"), vec!["(1 2)"]);
+    }
+
+    #[test]
+    fn test_unset() {
+        var::set_array("UNSET_A", &["x".into(), "y".into(), "z".into()], false).unwrap();
+        assert_eq!(unset(&["UNSET_A[1]".to_string()]), 0);
+        assert_eq!(var::values("UNSET_A"), vec!["x", "z"]);
+        assert_eq!(unset(&["UNSET_A".to_string()]), 0);
+        assert!(!var::is_set("UNSET_A"));
+        assert_eq!(unset(&["1x".to_string()]), 1);
+    }
+}
@@ -0,0 +1,171 @@
+use super::{cd, split_flags};
+use crate::{dirstack, println_error, var};
+
+fn is_index(arg: &str) -> bool {
+    let digits = arg.strip_prefix(['+', '-']).unwrap_or("");
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// 家目录显示为 `~`
+fn abbreviate(dir: &str) -> String {
+    match var::get("HOME") {
+        Some(home) if !home.is_empty() && home != "/" => match dir.strip_prefix(&home) {
+            Some("") => "~".to_string(),
+            Some(rest) if rest.starts_with('/') => format!("~{}", rest),
+            _ => dir.to_string(),
+        },
+        _ => dir.to_string(),
+    }
+}
+
+fn print_stack(long: bool, per_line: bool, verbose: bool) {
+    let entries: Vec<String> = dirstack::entries()
+        .iter()
+        .map(|dir| if long { dir.clone() } else { abbreviate(dir) })
+        .collect();
+    if verbose {
+        for (i, dir) in entries.iter().enumerate() {
+            println!("{:2}  {}", i, dir);
+        }
+    } else if per_line {
+        for dir in entries {
+            println!("{}", dir);
+        }
+    } else {
+        println!("{}", entries.join(" "));
+    }
+}
+
+fn index_out_of_range(name: &str, arg: &str) -> i32 {
+    println_error!("{}: {}: directory stack index out of range", name, arg);
+    1
+}
+
+/// 切换到栈顶目录；失败时栈保持不变
+fn enter(name: &str, entries: Vec<String>, change: bool) -> i32 {
+    if change && let Err(e) = cd::switch_dir(&entries[0], false) {
+        println_error!("{}: {}", name, e);
+        return 1;
+    }
+    dirstack::replace(entries);
+    print_stack(false, false, false);
+    0
+}
+
+/// `pushd [-n] [+N | -N | dir]`
+pub(crate) fn pushd(args: &[String]) -> i32 {
+    let Some((flags, args)) = split_flags("pushd", args, "n") else {
+        return 2;
+    };
+    let change = !flags.contains('n');
+    let mut entries = dirstack::entries();
+    match args {
+        [] => {
+            if entries.len() < 2 {
+                println_error!("pushd: no other directory");
+                return 1;
+            }
+            if change {
+                entries.swap(0, 1);
+            } else if entries.len() > 2 {
+                entries.swap(1, 2);
+            }
+            enter("pushd", entries, change)
+        }
+        [arg] if is_index(arg) => {
+            let Some(index) = dirstack::resolve_index(arg, entries.len()) else {
+                return index_out_of_range("pushd", arg);
+            };
+            if change {
+                entries.rotate_left(index);
+            } else if index > 0 {
+                entries[1..].rotate_left(index - 1);
+            }
+            enter("pushd", entries, change)
+        }
+        [dir] => {
+            if change {
+                match cd::switch_dir(dir, false) {
+                    Ok(target) => entries.insert(0, target.display().to_string()),
+                    Err(e) => {
+                        println_error!("pushd: {}", e);
+                        return 1;
+                    }
+                }
+            } else {
+                entries.insert(1, dir.clone());
+            }
+            dirstack::replace(entries);
+            print_stack(false, false, false);
+            0
+        }
+        _ => {
+            println_error!("pushd: too many arguments");
+            2
+        }
+    }
+}
+
+/// `popd [-n] [+N | -N]`
+pub(crate) fn popd(args: &[String]) -> i32 {
+    let Some((flags, args)) = split_flags("popd", args, "n") else {
+        return 2;
+    };
+    let change = !flags.contains('n');
+    let mut entries = dirstack::entries();
+    if entries.len() < 2 {
+        println_error!("popd: directory stack empty");
+        return 1;
+    }
+    let index = match args {
+        [] => 0,
+        [arg] if is_index(arg) => match dirstack::resolve_index(arg, entries.len()) {
+            Some(index) => index,
+            None => return index_out_of_range("popd", arg),
+        },
+        [arg] => {
+            println_error!("popd: {}: invalid argument", arg);
+            return 2;
+        }
+        _ => {
+            println_error!("popd: too many arguments");
+            return 2;
+        }
+    };
+    if index == 0 && change {
+        entries.remove(0);
+        enter("popd", entries, true)
+    } else {
+        entries.remove(index.max(1));
+        enter("popd", entries, false)
+    }
+}
+
+/// `dirs [-clpv] [+N | -N]`
+pub(crate) fn dirs(args: &[String]) -> i32 {
+    let Some((flags, args)) = split_flags("dirs", args, "clpv") else {
+        return 2;
+    };
+    if flags.contains('c') {
+        dirstack::clear();
+        return 0;
+    }
+    let long = flags.contains('l');
+    match args {
+        [] => {
+            print_stack(long, flags.contains('p'), flags.contains('v'));
+            0
+        }
+        [arg] if is_index(arg) => match dirstack::get(arg) {
+            Some(dir) => {
+                println!("{}", if long { dir } else { abbreviate(&dir) });
+                0
+            }
+            None => index_out_of_range("dirs", arg),
+        },
+        _ => {
+            println_error!("dirs: usage: dirs [-clpv] [+N | -N]");
+            2
+        }
+    }
+}
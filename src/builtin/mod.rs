@@ -0,0 +1,194 @@
+use crate::{exec, println_error, script};
+
+mod abbr;
+mod alias;
+mod bind;
+pub(crate) mod cd;
+mod command;
+mod complete;
+pub(crate) mod condition;
+mod declare;
+mod dirs;
+mod set;
+mod z;
+
+/// 全部内置命令的名称
+pub(crate) const BUILTINS: &[&str] = &[
+    ".", "[", "[[", "abbr", "alias", "bind", "cd", "command", "complete", "declare", "dirs",
+    "exit", "hash", "popd", "pushd", "pwd", "return", "set", "shopt", "source", "test", "type",
+    "typeset", "unalias", "unset", "which", "z", "zi",
+];
+
+pub(crate) fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name)
+}
+
+/// 选项字母与其参数，以及选项之后的其余参数
+type SplitOptions<'a> = (Vec<(char, Option<&'a str>)>, &'a [String]);
+
+/// 分离开头的选项与其余参数，`--` 结束选项；`-` 与 `-N` 这样的数字（目录栈下标）不是选项。
+/// `with_value` 中的选项需要参数，参数可以紧跟在选项字母之后，也可以是下一个参数；
+/// 遇到 `allowed` 与 `with_value` 之外的字母或缺少参数时报错并返回 `None`
+fn split_options<'a>(
+    name: &str,
+    args: &'a [String],
+    allowed: &str,
+    with_value: &str,
+) -> Option<SplitOptions<'a>> {
+    let mut options = Vec::new();
+    let mut rest = args;
+    while let Some((arg, tail)) = rest.split_first() {
+        if arg == "--" {
+            return Some((options, tail));
+        }
+        let Some(letters) = arg.strip_prefix('-') else {
+            break;
+        };
+        if letters.is_empty() || letters.bytes().all(|b| b.is_ascii_digit()) {
+            break;
+        }
+        rest = tail;
+        for (i, letter) in letters.char_indices() {
+            if with_value.contains(letter) {
+                let attached = &letters[i + letter.len_utf8()..];
+                let value = if !attached.is_empty() {
+                    attached
+                } else if let Some((value, tail)) = rest.split_first() {
+                    rest = tail;
+                    value.as_str()
+                } else {
+                    println_error!("{}: -{}: option requires an argument", name, letter);
+                    return None;
+                };
+                options.push((letter, Some(value)));
+                break;
+            }
+            if !allowed.contains(letter) {
+                println_error!("{}: -{}: invalid option", name, letter);
+                return None;
+            }
+            options.push((letter, None));
+        }
+    }
+    Some((options, rest))
+}
+
+/// 只有不带参数的选项时的 [`split_options`]，返回全部选项字母
+fn split_flags<'a>(
+    name: &str,
+    args: &'a [String],
+    allowed: &str,
+) -> Option<(String, &'a [String])> {
+    let (options, rest) = split_options(name, args, allowed, "")?;
+    Some((
+        options.into_iter().map(|(letter, _)| letter).collect(),
+        rest,
+    ))
+}
+
+/// 执行内置命令，返回退出状态；若 `name` 不是内置命令则返回 `None`
+pub(crate) async fn try_execute(name: &str, args: &[String]) -> Option<i32> {
+    let status = match name {
+        "exit" => {
+            let status = match args.first() {
+                Some(code) => code.parse::<i32>().unwrap_or_else(|_| {
+                    println_error!("exit: {}: numeric argument required", code);
+                    2
+                }),
+                None => exec::last_status(),
+            };
+            std::process::exit(status & 0xff)
+        }
+        "cd" => cd::cd(args),
+        "pwd" => cd::pwd(args),
+        "pushd" => dirs::pushd(args),
+        "popd" => dirs::popd(args),
+        "dirs" => dirs::dirs(args),
+        "type" => command::type_(args),
+        "command" => command::command(args),
+        "hash" => command::hash(args),
+        "which" => command::which(args),
+        "set" => set::set(args),
+        "shopt" => set::shopt(args),
+        "z" => z::z(args),
+        "zi" => z::zi(args),
+        "return" => {
+            exec::request_return();
+            match args.first() {
+                Some(code) => code.parse::<i32>().map(|n| n & 0xff).unwrap_or_else(|_| {
+                    println_error!("return: {}: numeric argument required", code);
+                    2
+                }),
+                None => exec::last_status(),
+            }
+        }
+        "source" | "." => match args.split_first() {
+            Some((path, rest)) => {
+                // source 会递归执行命令，需要装箱
+                let file = script::find_sourced(path).display().to_string();
+                match Box::pin(script::source(&file, rest)).await {
+                    Ok(()) => exec::last_status(),
+                    Err(e) => {
+                        println_error!("{}: {}: {}", name, path, e);
+                        1
+                    }
+                }
+            }
+            None => {
+                println_error!("{}: filename argument required", name);
+                2
+            }
+        },
+        "abbr" => abbr::abbr(args),
+        "bind" => bind::bind(args),
+        "complete" => complete::complete(args),
+        "alias" => alias::alias(args),
+        "unalias" => alias::unalias(args),
+        "declare" | "typeset" => declare::declare(args),
+        "unset" => declare::unset(args),
+        "test" => condition::test(args),
+        "[" => match args.split_last() {
+            Some((last, rest)) if last == "]" => condition::test(rest),
+            _ => {
+                println_error!("[: missing `]'");
+                2
+            }
+        },
+        "[[" => match args.split_last() {
+            Some((last, rest)) if last == "]]" => condition::extended_test(rest),
+            _ => {
+                println_error!("[[: missing `]]'");
+                2
+            }
+        },
+        _ => return None,
+    };
+    Some(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_options() {
+        let args: Vec<String> = ["-pWx y", "-m", "vi", "-f", "--", "-r", "name"]
+            .map(String::from)
+            .to_vec();
+        let (options, rest) = split_options("t", &args, "pf", "Wm").unwrap();
+        assert_eq!(
+            options,
+            vec![
+                ('p', None),
+                ('W', Some("x y")),
+                ('m', Some("vi")),
+                ('f', None)
+            ]
+        );
+        assert_eq!(rest, ["-r", "name"]);
+        assert!(split_options("t", &args[1..2], "", "m").is_none());
+        assert!(split_flags("t", &args[..1], "p").is_none());
+        let (flags, rest) = split_flags("t", &args[4..], "p").unwrap();
+        assert_eq!((flags.as_str(), rest), ("", &args[5..]));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{options, println_error, var};
+
+/// `set [-o name] [+o name] [-- arg ...]`，不带名称的 `-o` 列出选项，`+o` 以可重新读入的格式输出
+pub(crate) fn set(args: &[String]) -> i32 {
+    if args.is_empty() {
+        for name in var::names() {
+            if let Some(line) = var::describe(&name) {
+                println!("{}", line);
+            }
+        }
+        return 0;
+    }
+    let mut rest = args;
+    while let Some((arg, tail)) = rest.split_first() {
+        match arg.as_str() {
+            "--" => {
+                var::replace_positional_args(tail.to_vec());
+                return 0;
+            }
+            flag @ ("-o" | "+o") => {
+                let on = flag == "-o";
+                let Some((name, tail)) = tail.split_first() else {
+                    for (name, enabled) in options::all() {
+                        if on {
+                            println!("{:<15}\t{}", name, if enabled { "on" } else { "off" });
+                        } else {
+                            println!("set {}o {}", if enabled { '-' } else { '+' }, name);
+                        }
+                    }
+                    return 0;
+                };
+                if let Err(e) = options::set(name, on) {
+                    println_error!("set: {}", e);
+                    return 1;
+                }
+                rest = tail;
+            }
+            option if option.starts_with(['-', '+']) && option.len() > 1 => {
+                println_error!("set: {}: invalid option", option);
+                println_error!("set: usage: set [-o name] [+o name] [-- arg ...]");
+                return 2;
+            }
+            _ => {
+                var::replace_positional_args(rest.to_vec());
+                return 0;
+            }
+        }
+    }
+    0
+}
+
+/// `shopt [-pq] [-s | -u] [name ...]`
+pub(crate) fn shopt(args: &[String]) -> i32 {
+    let mut mode = None;
+    let mut print = false;
+    let mut quiet = false;
+    let mut names = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-s" => mode = Some(true),
+            "-u" => mode = Some(false),
+            "-p" => print = true,
+            "-q" => quiet = true,
+            option if option.starts_with('-') => {
+                println_error!("shopt: {}: invalid option", option);
+                println_error!("shopt: usage: shopt [-pq] [-s | -u] [name ...]");
+                return 2;
+            }
+            name => names.push(name),
+        }
+    }
+
+    if let Some(on) = mode
+        && !names.is_empty()
+    {
+        let mut status = 0;
+        for name in names {
+            if let Err(e) = options::set(name, on) {
+                println_error!("shopt: {}", e);
+                status = 1;
+            }
+        }
+        return status;
+    }
+
+    let mut status = 0;
+    for (name, enabled) in options::all() {
+        if !names.is_empty() && !names.contains(&name) {
+            continue;
+        }
+        if mode.is_some_and(|on| on != enabled) {
+            continue;
+        }
+        if !enabled && !names.is_empty() {
+            status = 1;
+        }
+        if quiet {
+            continue;
+        }
+        if print {
+            println!("shopt {} {}", if enabled { "-s" } else { "-u" }, name);
+        } else {
+            println!("{:<15}\t{}", name, if enabled { "on" } else { "off" });
+        }
+    }
+    for name in names {
+        if !options::all().iter().any(|(known, _)| *known == name) {
+            println_error!("shopt: {}: invalid shell option name", name);
+            status = 1;
+        }
+    }
+    status
+}
@@ -0,0 +1,113 @@
+use super::cd;
+use crate::{frecency, input, println_error, var};
+use std::io::Write;
+use std::path::Path;
+
+/// 匹配片段的目录，排除当前目录
+fn candidates(fragments: &[String]) -> Vec<(f64, String)> {
+    let current = cd::logical_pwd().display().to_string();
+    frecency::query(fragments)
+        .into_iter()
+        .filter(|(_, path)| *path != current)
+        .collect()
+}
+
+fn jump(name: &str, dir: &str) -> i32 {
+    match cd::switch_dir(dir, false) {
+        Ok(_) => 0,
+        Err(e) => {
+            println_error!("{}: {}", name, e);
+            1
+        }
+    }
+}
+
+/// `z [-l | -x] [fragment ...]`：跳转到匹配片段且得分最高的目录
+pub(crate) fn z(args: &[String]) -> i32 {
+    match args.first().map(String::as_str) {
+        None => match var::get("HOME") {
+            Some(home) => jump("z", &home),
+            None => {
+                println_error!("z: HOME not set");
+                1
+            }
+        },
+        Some("-") if args.len() == 1 => cd::cd(args),
+        Some("-l") => {
+            let mut found = candidates(&args[1..]);
+            found.reverse();
+            for (score, path) in found {
+                println!("{:<10.1} {}", score, path);
+            }
+            0
+        }
+        Some("-x") => {
+            let current = cd::logical_pwd().display().to_string();
+            if frecency::remove(&current) {
+                0
+            } else {
+                println_error!("z: {}: not in database", current);
+                1
+            }
+        }
+        Some(option) if option.starts_with('-') && option.len() > 1 => {
+            println_error!("z: {}: invalid option", option);
+            println_error!("z: usage: z [-l | -x] [fragment ...]");
+            2
+        }
+        // 已存在的目录直接进入
+        Some(dir) if args.len() == 1 && Path::new(dir).is_dir() => jump("z", dir),
+        Some(_) => match candidates(args).first() {
+            Some((_, path)) => jump("z", path),
+            None => {
+                println_error!("z: no match found");
+                1
+            }
+        },
+    }
+}
+
+/// `zi [fragment ...]`：在模糊查找界面中选择候选目录；标准输入不是终端时列出候选目录，按序号选择
+pub(crate) fn zi(args: &[String]) -> i32 {
+    let found = candidates(args);
+    if found.is_empty() {
+        println_error!("zi: no match found");
+        return 1;
+    }
+    if input::is_interactive() {
+        let paths: Vec<String> = found.into_iter().map(|(_, path)| path).collect();
+        return match input::pick_directory(&paths) {
+            Ok(Some(path)) => jump("zi", &path),
+            Ok(None) => 1,
+            Err(e) => {
+                println_error!("zi: {}", e);
+                1
+            }
+        };
+    }
+    let mut stderr = std::io::stderr();
+    for (i, (score, path)) in found.iter().enumerate() {
+        let _ = writeln!(stderr, "{:3}  {:<8.1} {}", i + 1, score, path);
+    }
+    let _ = write!(stderr, "zi> ");
+    let _ = stderr.flush();
+    let choice = match input::read_plain_line() {
+        Ok(Some(line)) => line,
+        _ => return 1,
+    };
+    let choice = choice.trim();
+    if choice.is_empty() {
+        return 1;
+    }
+    match choice
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| found.get(n.wrapping_sub(1)))
+    {
+        Some((_, path)) => jump("zi", path),
+        None => {
+            println_error!("zi: {}: invalid selection", choice);
+            1
+        }
+    }
+}
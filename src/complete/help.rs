@@ -0,0 +1,395 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::process::Command;
+
+/// 等待 `--help` 或解压手册页的最长时间
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 查找手册页的章节
+const MAN_SECTIONS: &[&str] = &["1", "8", "6"];
+
+lazy_static! {
+    /// 选项名：`-x`、`-long-opt`、`--long-opt`
+    static ref OPTION: Regex = Regex::new(r"^--?[A-Za-z0-9?][A-Za-z0-9_.+-]*$").unwrap();
+    /// roff 的字体、特殊字符与其他转义序列
+    static ref ROFF_ESCAPE: Regex =
+        Regex::new(r"\\(f(\[[^\]]*\]|\(..|.)|\(..|\*(\(..|.)|[&|^%])").unwrap();
+}
+
+/// 一个命令的选项及其说明，说明可以为空
+pub type Options = Vec<(String, String)>;
+
+/// 选项列表中的一项，例如 `-f FILE, --file=FILE` 或 `--color[=WHEN]`，返回其中的选项名
+fn options_in(tag: &str) -> Vec<String> {
+    tag.split(|c: char| c == ',' || c == '|' || c.is_whitespace())
+        .filter_map(|token| token.split(['=', '[', '<']).next())
+        .filter(|token| OPTION.is_match(token))
+        .map(str::to_string)
+        .collect()
+}
+
+fn add(options: &mut Options, names: Vec<String>, description: &str) {
+    let description = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    for name in names {
+        match options.iter_mut().find(|(known, _)| *known == name) {
+            Some((_, known)) if known.is_empty() => *known = description.clone(),
+            Some(_) => {}
+            None => options.push((name, description.clone())),
+        }
+    }
+}
+
+/// 解析 `--help` 的输出：以 `-` 开头的行在两个以上的空格之后是说明，
+/// 没有说明时使用缩进更深的下一行
+pub fn parse_help(text: &str) -> Options {
+    let mut options = Options::new();
+    let lines: Vec<&str> = text.lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if !trimmed.starts_with('-') {
+            continue;
+        }
+        let (tag, description) = match trimmed.find("  ").or_else(|| trimmed.find('\t')) {
+            Some(end) => (&trimmed[..end], trimmed[end..].trim()),
+            None => (trimmed, ""),
+        };
+        let description = match lines.get(i + 1) {
+            Some(next) if description.is_empty() => {
+                let indent = line.len() - trimmed.len();
+                let next_trimmed = next.trim_start();
+                if next.len() - next_trimmed.len() > indent && !next_trimmed.starts_with('-') {
+                    next_trimmed.trim()
+                } else {
+                    ""
+                }
+            }
+            _ => description,
+        };
+        add(&mut options, options_in(tag), description);
+    }
+    options
+}
+
+/// 去掉 roff 的转义序列
+fn strip_roff(text: &str) -> String {
+    let text = text
+        .replace("\\-", "-")
+        .replace("\\e", "\\")
+        .replace("\\ ", " ");
+    ROFF_ESCAPE.replace_all(&text, "").into_owned()
+}
+
+/// mdoc 的 `.It Fl a , Fl -all Ar file`：`Fl` 之后的单词是选项，其他宏名被去掉
+fn mdoc_tag(args: &str) -> String {
+    let mut out = Vec::new();
+    let mut flag = false;
+    for token in args.split_whitespace() {
+        match token {
+            "Fl" => flag = true,
+            _ if token.len() == 2 && token.starts_with(|c: char| c.is_ascii_uppercase()) => {}
+            _ => {
+                out.push(if flag {
+                    format!("-{}", token)
+                } else {
+                    token.to_string()
+                });
+                flag = false;
+            }
+        }
+    }
+    out.join(" ")
+}
+
+/// 解析手册页：`.TP` 之后的一行、`.IP` 的第一个参数与 mdoc 的 `.It` 是选项，
+/// 连续的几个选项共用随后第一行正文作为说明
+pub fn parse_man(source: &str) -> Options {
+    let mut options = Options::new();
+    let mut pending: Vec<String> = Vec::new();
+    let mut expect_tag = false;
+    for line in source.lines() {
+        let tag = if let Some(rest) = line.strip_prefix(".IP") {
+            let rest = rest.trim();
+            Some(match rest.strip_prefix('"') {
+                Some(quoted) => quoted.split('"').next().unwrap_or_default().to_string(),
+                None => rest
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        } else if let Some(rest) = line.strip_prefix(".It") {
+            Some(mdoc_tag(rest))
+        } else if line.starts_with(".TP") {
+            expect_tag = true;
+            continue;
+        } else if line.starts_with(".SH") || line.starts_with(".SS") || line.starts_with(".PP") {
+            pending.clear();
+            continue;
+        } else if line.starts_with('.') || line.starts_with('\'') {
+            continue;
+        } else if expect_tag {
+            Some(line.to_string())
+        } else {
+            None
+        };
+        expect_tag = false;
+        match tag {
+            Some(tag) => pending.extend(options_in(&strip_roff(&tag))),
+            None if !pending.is_empty() => {
+                let description = strip_roff(line);
+                add(&mut options, std::mem::take(&mut pending), &description);
+            }
+            None => {}
+        }
+    }
+    add(&mut options, pending, "");
+    options
+}
+
+/// 缓存目录 `$XDG_CACHE_HOME/sh-rs/options`（默认 `~/.cache/sh-rs/options`）
+fn cache_dir() -> Option<PathBuf> {
+    let base = match env::var("XDG_CACHE_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".cache"),
+    };
+    Some(base.join("sh-rs").join("options"))
+}
+
+/// 缓存文件以可执行文件规范化后的路径命名，第一行是可执行文件的修改时间，其后每行是 `选项\t说明`
+fn cache_file(path: &Path) -> Option<PathBuf> {
+    let path = fs::canonicalize(path).ok()?;
+    Some(cache_dir()?.join(path.display().to_string().replace('/', "%")))
+}
+
+fn read_cache(file: &Path, mtime: u64) -> Option<Options> {
+    let contents = fs::read_to_string(file).ok()?;
+    let mut lines = contents.lines();
+    if lines.next()?.parse::<u64>().ok()? != mtime {
+        return None;
+    }
+    Some(
+        lines
+            .filter_map(|line| {
+                let (name, description) = line.split_once('\t')?;
+                Some((name.to_string(), description.to_string()))
+            })
+            .collect(),
+    )
+}
+
+/// 先写入临时文件再改名，避免多个 shell 同时写入时读到不完整的缓存
+fn write_cache(file: &Path, mtime: u64, options: &Options) -> std::io::Result<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temporary = PathBuf::from(format!("{}.{}", file.display(), std::process::id()));
+    let mut writer = fs::File::create(&temporary)?;
+    writeln!(writer, "{}", mtime)?;
+    for (name, description) in options {
+        writeln!(writer, "{}\t{}", name, description)?;
+    }
+    fs::rename(temporary, file)
+}
+
+/// 运行命令并返回标准输出与标准错误，无法启动或超时时返回 `None`；
+/// 退出状态不计，很多命令输出 `--help` 后以非零状态退出
+async fn run(program: &Path, args: &[&str]) -> Option<String> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .env("PAGER", "cat")
+        .env("MANPAGER", "cat")
+        .env("GIT_PAGER", "cat")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let child = command.spawn().ok()?;
+    match tokio::time::timeout(TIMEOUT, child.wait_with_output()).await {
+        Ok(Ok(output)) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            Some(text)
+        }
+        _ => None,
+    }
+}
+
+/// 命令的手册页：在 `$MANPATH`、可执行文件旁的 `share/man` 与系统目录中查找
+fn man_page(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let mut dirs: Vec<PathBuf> = env::var("MANPATH")
+        .map(|manpath| env::split_paths(&manpath).collect())
+        .unwrap_or_default();
+    if let Some(prefix) = path.parent().and_then(Path::parent) {
+        dirs.push(prefix.join("share/man"));
+    }
+    dirs.push(PathBuf::from("/usr/local/share/man"));
+    dirs.push(PathBuf::from("/usr/share/man"));
+    for dir in dirs {
+        for section in MAN_SECTIONS {
+            let page = dir
+                .join(format!("man{}", section))
+                .join(format!("{}.{}", name, section));
+            let compressed = page.with_extension(format!("{}.gz", section));
+            if page.is_file() {
+                return Some(page);
+            }
+            if compressed.is_file() {
+                return Some(compressed);
+            }
+        }
+    }
+    None
+}
+
+/// 可执行文件的选项：合并 `--help` 的输出与手册页中的选项，按路径与修改时间缓存在磁盘上；
+/// `run_help` 为假时不运行命令，只解析手册页
+pub async fn options(path: &Path, run_help: bool) -> Options {
+    let Some(mtime) = path
+        .metadata()
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_secs())
+    else {
+        return Options::new();
+    };
+    let cache = cache_file(path);
+    if let Some(options) = cache.as_deref().and_then(|file| read_cache(file, mtime)) {
+        return options;
+    }
+
+    let help = if run_help {
+        run(path, &["--help"]).await
+    } else {
+        None
+    };
+    let mut options = help.as_deref().map(parse_help).unwrap_or_default();
+    if let Some(page) = man_page(path) {
+        let source = if page.extension().is_some_and(|ext| ext == "gz") {
+            run(Path::new("gzip"), &["-dc", &page.display().to_string()])
+                .await
+                .unwrap_or_default()
+        } else {
+            fs::read_to_string(&page).unwrap_or_default()
+        };
+        for (name, description) in parse_man(&source) {
+            add(&mut options, vec![name], &description);
+        }
+    }
+    options.sort_by(|a, b| a.0.cmp(&b.0));
+    // 只解析了手册页，或 `--help` 无法运行、超时（例如冷启动很慢的命令）时不缓存，下次补全时重试
+    if help.is_some()
+        && let Some(file) = cache
+    {
+        let _ = write_cache(&file, mtime, &options);
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_help() {
+        let help = "Usage: ls [OPTION]... [FILE]...
+  -a, --all                  do not ignore entries starting with .
+      --block-size=SIZE      with -l, scale sizes by SIZE
+  -C                         list entries by columns
+      --color[=WHEN]         color the output WHEN
+  -f FILE, --file=FILE
+                        read patterns from FILE
+  - not an option
+";
+        let options = parse_help(help);
+        let names: Vec<&str> = options.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "-a",
+                "--all",
+                "--block-size",
+                "-C",
+                "--color",
+                "-f",
+                "--file"
+            ]
+        );
+        assert_eq!(options[1].1, "do not ignore entries starting with .");
+        assert_eq!(options[6].1, "read patterns from FILE");
+    }
+
+    #[test]
+    fn test_parse_man() {
+        let source = r#".SH OPTIONS
+.TP
+\fB\-a\fR, \fB\-\-all\fR
+do not ignore entries starting with .
+.IP "\fB\-C\fR" 4
+.PD 0
+.IP "\fB\-\-demangle[=\fR\fIstyle\fR\fB]\fR" 4
+.PD
+Decode (\fIdemangle\fR) low-level symbol names.
+.It Fl v , Fl -verbose
+Be verbose.
+"#;
+        assert_eq!(
+            parse_man(source),
+            vec![
+                (
+                    "-a".to_string(),
+                    "do not ignore entries starting with .".to_string()
+                ),
+                (
+                    "--all".to_string(),
+                    "do not ignore entries starting with .".to_string()
+                ),
+                (
+                    "-C".to_string(),
+                    "Decode (demangle) low-level symbol names.".to_string()
+                ),
+                (
+                    "--demangle".to_string(),
+                    "Decode (demangle) low-level symbol names.".to_string()
+                ),
+                ("-v".to_string(), "Be verbose.".to_string()),
+                ("--verbose".to_string(), "Be verbose.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_options_cache() {
+        let file = env::temp_dir().join(format!("sh-rs-options-{}", std::process::id()));
+        let options = vec![("--all".to_string(), "everything".to_string())];
+        write_cache(&file, 42, &options).unwrap();
+        assert_eq!(read_cache(&file, 42), Some(options));
+        assert_eq!(read_cache(&file, 43), None);
+        // 同一文件的不同写法使用同一个缓存文件
+        let dir = file.parent().unwrap();
+        let name = file.file_name().unwrap();
+        assert_eq!(cache_file(&file), cache_file(&dir.join(".").join(name)));
+        let _ = fs::remove_file(&file);
+    }
+
+    #[tokio::test]
+    async fn test_failed_help_not_cached() {
+        // 没有执行权限的文件无法运行 `--help`
+        let file = env::temp_dir().join(format!("sh-rs-no-help-{}", std::process::id()));
+        fs::write(&file, "").unwrap();
+        assert!(run(&file, &["--help"]).await.is_none());
+        let options = options(&file, true).await;
+        let cached = cache_file(&file).is_some_and(|cache| cache.exists());
+        let _ = fs::remove_file(&file);
+        assert!(options.is_empty());
+        assert!(!cached);
+    }
+}
@@ -0,0 +1,660 @@
+use crate::builtin::condition::glob_match;
+use crate::resolve::Resolution;
+use crate::token::expand_env_vars;
+use crate::{alias, builtin, exec, println_error, resolve, script, shrc, var};
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+mod help;
+
+/// 之后的单词重新处于命令名位置的保留字
+const COMMAND_KEYWORDS: &[&str] = &[
+    "!", "do", "elif", "else", "if", "then", "time", "until", "while", "{",
+];
+
+/// 在命令行中需要转义的字符
+const SPECIAL_CHARS: &str = " \t\n'\"\\$`;&|<>()*?[]{}!#";
+
+/// 插入补全结果时的引用方式，与光标所在单词已经使用的引号一致
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quoting {
+    /// 原样插入，用于 `$VAR`
+    Raw,
+    Backslash,
+    Single,
+    Double,
+}
+
+/// 光标所在的单词：去掉引号后的各个单词，最后一个是光标所在的单词（可能为空）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Context {
+    pub words: Vec<String>,
+    /// 光标所在单词在命令行中的起始位置
+    pub start: usize,
+    /// 光标所在单词中尚未闭合的引号
+    pub quote: Option<char>,
+    /// 光标所在的单词是命令名
+    pub command_position: bool,
+}
+
+/// 补全的结果：替换命令行中 `start` 到光标的部分
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub start: usize,
+    /// 已输入的部分（去掉引号），候选项都以此开头
+    pub word: String,
+    pub quoting: Quoting,
+    pub candidates: Vec<String>,
+    /// 唯一的候选项补全后不加空格
+    pub no_space: bool,
+    /// 与候选项一一对应的说明，没有说明时为空
+    pub descriptions: Vec<String>,
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=')
+        .is_some_and(|(name, _)| var::is_valid_name(name))
+}
+
+/// 分析光标之前的命令行：按空白与 `;`、`|`、`&`、`(`、`)`、`<`、`>` 分隔单词，
+/// 控制操作符与部分保留字之后是新的命令，重定向之后的单词不是命令名
+pub fn parse(line: &str) -> Context {
+    let mut words: Vec<String> = Vec::new();
+    let mut word: Option<(usize, String)> = None;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut redirect = false;
+    // 结束一个单词；命令开头的赋值与保留字不算作命令的单词
+    let finish =
+        |words: &mut Vec<String>, word: &mut Option<(usize, String)>, redirect: &mut bool| {
+            if let Some((_, text)) = word.take() {
+                let skipped = words.is_empty()
+                    && (COMMAND_KEYWORDS.contains(&text.as_str()) || is_assignment(&text));
+                if *redirect {
+                    *redirect = false;
+                } else if !skipped {
+                    words.push(text);
+                }
+            }
+        };
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+            word.get_or_insert((i, String::new())).1.push(c);
+            continue;
+        }
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => escaped = true,
+            (Some(_), c) => word.get_or_insert((i, String::new())).1.push(c),
+            (None, '\'' | '"') => {
+                word.get_or_insert((i, String::new()));
+                quote = Some(c);
+            }
+            (None, '\\') => {
+                word.get_or_insert((i, String::new()));
+                escaped = true;
+            }
+            (None, c) if c.is_whitespace() => finish(&mut words, &mut word, &mut redirect),
+            (None, '<' | '>') => {
+                finish(&mut words, &mut word, &mut redirect);
+                redirect = true;
+            }
+            (None, ';' | '|' | '&' | '(' | ')') => {
+                finish(&mut words, &mut word, &mut redirect);
+                words.clear();
+                redirect = false;
+            }
+            (None, c) => word.get_or_insert((i, String::new())).1.push(c),
+        }
+    }
+    let (start, current) = word.unwrap_or((line.len(), String::new()));
+    let command_position = words.is_empty() && !redirect;
+    words.push(current);
+    Context {
+        words,
+        start,
+        quote,
+        command_position,
+    }
+}
+
+/// 补全光标之前的命令行：`$` 之后补全变量名，命令名位置补全命令，
+/// 命令有 `complete` 注册的规则时按规则补全，否则补全路径，`-` 开头的单词补全外部命令的选项
+pub async fn complete(line: &str) -> Completion {
+    let context = parse(line);
+    let word = context.words.last().cloned().unwrap_or_default();
+    let quoting = match context.quote {
+        Some('\'') => Quoting::Single,
+        Some(_) => Quoting::Double,
+        // 以引号开头并已闭合的单词按原来的引号重新引用
+        None if line[context.start..].starts_with('\'') => Quoting::Single,
+        None if line[context.start..].starts_with('"') => Quoting::Double,
+        None => Quoting::Backslash,
+    };
+    if quoting != Quoting::Single
+        && let Some((offset, braced, prefix)) = variable_prefix(&line[context.start..])
+    {
+        return Completion {
+            start: context.start + offset,
+            word: line[context.start + offset..].to_string(),
+            quoting: Quoting::Raw,
+            candidates: variables(prefix)
+                .into_iter()
+                .map(|name| {
+                    if braced {
+                        format!("${{{}}}", name)
+                    } else {
+                        format!("${}", name)
+                    }
+                })
+                .collect(),
+            no_space: false,
+            descriptions: Vec::new(),
+        };
+    }
+    let mut completion = Completion {
+        start: context.start,
+        word: word.clone(),
+        quoting,
+        candidates: Vec::new(),
+        no_space: false,
+        descriptions: Vec::new(),
+    };
+    if context.command_position {
+        completion.candidates = if word.contains('/') {
+            paths(&word, true)
+        } else {
+            commands(&word)
+        };
+    } else if let Some(spec) = spec_for(&context.words[0]).await {
+        completion.candidates = spec.generate(&context, line).await;
+        completion.no_space = spec.options.contains(&"nospace");
+    } else if word.starts_with('-')
+        && let Some(Resolution::File { path, .. }) = resolve::resolve(&context.words[0])
+    {
+        // 没有补全规则的外部命令从 `--help` 与手册页中取得选项；
+        // 只对经 `PATH` 找到的命令运行 `--help`，带路径的命令（如 `./deploy.sh`）只解析手册页
+        let run_help = !context.words[0].contains('/') && path.is_absolute();
+        let (candidates, descriptions) = help::options(&path, run_help)
+            .await
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(&word))
+            .unzip();
+        completion.candidates = candidates;
+        completion.descriptions = descriptions;
+    } else {
+        completion.candidates = paths(&word, false);
+    }
+    completion
+}
+
+/// `complete -A` 的动作名称及对应的单字母选项
+pub const ACTIONS: &[(&str, Option<char>)] = &[
+    ("alias", Some('a')),
+    ("builtin", Some('b')),
+    ("command", Some('c')),
+    ("directory", Some('d')),
+    ("file", Some('f')),
+    ("function", None),
+    ("variable", Some('v')),
+];
+
+/// `complete -o` 的选项
+pub const OPTIONS: &[&str] = &[
+    "bashdefault",
+    "default",
+    "dirnames",
+    "filenames",
+    "nospace",
+    "plusdirs",
+];
+
+/// `complete` 为命令注册的补全规则
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spec {
+    /// `-A` 与单字母选项指定的动作，取值为 `ACTIONS` 中的名称
+    pub actions: Vec<&'static str>,
+    /// `-W`：补全时展开变量并按空白分隔
+    pub words: Option<String>,
+    /// `-F`：通过 `COMP_WORDS`、`COMP_CWORD` 调用的函数，结果从 `COMPREPLY` 读取
+    pub function: Option<String>,
+    /// `-X`：去掉匹配的候选项，以 `!` 开头时只保留匹配的候选项，`&` 代表当前单词
+    pub filter: Option<String>,
+    /// `-P`、`-S`：加在每个候选项前后的文本
+    pub prefix: String,
+    pub suffix: String,
+    /// `-o` 的选项，取值为 `OPTIONS` 中的名称
+    pub options: Vec<&'static str>,
+}
+
+lazy_static! {
+    static ref SPECS: RwLock<BTreeMap<String, Spec>> = RwLock::new(BTreeMap::new());
+    /// 已经尝试从补全目录加载过的命令
+    static ref LOADED: RwLock<HashSet<String>> = RwLock::new(HashSet::new());
+}
+
+pub fn set(name: &str, spec: Spec) {
+    SPECS.write().unwrap().insert(name.to_string(), spec);
+}
+
+pub fn get(name: &str) -> Option<Spec> {
+    SPECS.read().unwrap().get(name).cloned()
+}
+
+pub fn remove(name: &str) -> bool {
+    SPECS.write().unwrap().remove(name).is_some()
+}
+
+pub fn clear() {
+    SPECS.write().unwrap().clear();
+}
+
+/// 全部规则，按命令名排序
+pub fn all() -> Vec<(String, Spec)> {
+    SPECS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(name, spec)| (name.clone(), spec.clone()))
+        .collect()
+}
+
+impl Spec {
+    /// `complete -p` 输出的形式，可以重新执行
+    pub fn to_command(&self, name: &str) -> String {
+        let mut parts = vec!["complete".to_string()];
+        for option in &self.options {
+            parts.push(format!("-o {}", option));
+        }
+        for action in &self.actions {
+            match ACTIONS.iter().find(|(known, _)| known == action) {
+                Some((_, Some(flag))) => parts.push(format!("-{}", flag)),
+                _ => parts.push(format!("-A {}", action)),
+            }
+        }
+        if let Some(words) = &self.words {
+            parts.push(format!("-W {}", alias::quote(words)));
+        }
+        if let Some(function) = &self.function {
+            parts.push(format!("-F {}", function));
+        }
+        if let Some(filter) = &self.filter {
+            parts.push(format!("-X {}", alias::quote(filter)));
+        }
+        if !self.prefix.is_empty() {
+            parts.push(format!("-P {}", alias::quote(&self.prefix)));
+        }
+        if !self.suffix.is_empty() {
+            parts.push(format!("-S {}", alias::quote(&self.suffix)));
+        }
+        parts.push(name.to_string());
+        parts.join(" ")
+    }
+
+    /// 按规则生成光标所在单词的候选项。没有候选项时，`-o default`/`bashdefault` 回退到路径补全，
+    /// `-o dirnames` 回退到目录补全；`-o plusdirs` 总是加上目录
+    async fn generate(&self, context: &Context, line: &str) -> Vec<String> {
+        let word = context.words.last().map(String::as_str).unwrap_or_default();
+        let mut candidates = Vec::new();
+        for action in &self.actions {
+            candidates.extend(match *action {
+                "alias" => alias::all().into_iter().map(|(name, _)| name).collect(),
+                "builtin" => builtin::BUILTINS.iter().map(|s| s.to_string()).collect(),
+                "command" => resolve::command_names().into_iter().collect(),
+                "directory" => directories(word),
+                "file" => paths(word, false),
+                "function" => exec::function_names(),
+                "variable" => variables(""),
+                _ => Vec::new(),
+            });
+        }
+        if let Some(words) = &self.words {
+            candidates.extend(
+                expand_env_vars(words)
+                    .split_whitespace()
+                    .map(str::to_string),
+            );
+        }
+        candidates.retain(|candidate| candidate.starts_with(word));
+        if let Some(function) = &self.function {
+            candidates.extend(call_function(function, context, line).await);
+        }
+        if let Some(filter) = &self.filter {
+            let (keep, pattern) = match filter.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, filter.as_str()),
+            };
+            let pattern = pattern.replace('&', word);
+            candidates.retain(|candidate| glob_match(&pattern, candidate) == keep);
+        }
+        if self.options.contains(&"filenames") {
+            for candidate in &mut candidates {
+                if !candidate.ends_with('/') && Path::new(&expand_home(candidate)).is_dir() {
+                    candidate.push('/');
+                }
+            }
+        }
+        if candidates.is_empty() {
+            if self.options.contains(&"default") || self.options.contains(&"bashdefault") {
+                candidates = paths(word, false);
+            } else if self.options.contains(&"dirnames") {
+                candidates = directories(word);
+            }
+        }
+        if self.options.contains(&"plusdirs") {
+            candidates.extend(directories(word));
+        }
+        let mut seen = HashSet::new();
+        candidates
+            .into_iter()
+            .map(|candidate| format!("{}{}{}", self.prefix, candidate, self.suffix))
+            .filter(|candidate| seen.insert(candidate.clone()))
+            .collect()
+    }
+}
+
+/// 补全函数使用的变量
+const COMPLETION_VARIABLES: [&str; 5] = [
+    "COMP_WORDS",
+    "COMP_CWORD",
+    "COMP_LINE",
+    "COMP_POINT",
+    "COMPREPLY",
+];
+
+/// 以 `COMP_WORDS`、`COMP_CWORD`、`COMP_LINE`、`COMP_POINT` 调用补全函数，
+/// 参数是命令名、当前单词与前一个单词，返回函数设置的 `COMPREPLY`
+async fn call_function(function: &str, context: &Context, line: &str) -> Vec<String> {
+    if exec::function(function).is_none() {
+        return Vec::new();
+    }
+    let cword = context.words.len() - 1;
+    let previous = cword
+        .checked_sub(1)
+        .map_or("", |i| context.words[i].as_str());
+    // 调用期间覆盖这些变量，之后恢复用户原来的值（包括同名的环境变量）
+    let saved = COMPLETION_VARIABLES.map(var::save);
+    for name in COMPLETION_VARIABLES {
+        var::unset(name);
+    }
+    let _ = var::set_array("COMP_WORDS", &context.words, false);
+    var::set_scalar("COMP_CWORD", &cword.to_string(), false);
+    var::set_scalar("COMP_LINE", line, false);
+    var::set_scalar("COMP_POINT", &line.chars().count().to_string(), false);
+    let call = [
+        function,
+        context.words[0].as_str(),
+        context.words[cword].as_str(),
+        previous,
+    ]
+    .map(alias::quote)
+    .join(" ");
+    let status = exec::last_status();
+    if let Err(e) = exec::execute_line(&call).await {
+        println_error!("{}", e);
+    }
+    exec::set_last_status(status);
+    let reply = if var::is_set("COMPREPLY") {
+        var::values("COMPREPLY")
+    } else {
+        Vec::new()
+    };
+    for saved in saved {
+        var::restore(saved);
+    }
+    reply
+}
+
+/// 按需加载补全规则的目录：`$COMPLETION_PATH` 中以 `:` 分隔的目录，
+/// 未设置时为 `$XDG_CONFIG_HOME/sh-rs/completions`
+fn completion_dirs() -> Vec<PathBuf> {
+    match var::get("COMPLETION_PATH") {
+        Some(path) => path
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+        None => shrc::config_dir()
+            .map(|dir| dir.join("completions"))
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// 命令的补全规则；第一次补全没有规则的命令时，从补全目录中读取名为 `命令名` 或 `命令名.sh` 的文件
+async fn spec_for(command: &str) -> Option<Spec> {
+    let name = command.rsplit('/').next().unwrap_or(command);
+    if let Some(spec) = get(command).or_else(|| get(name)) {
+        return Some(spec);
+    }
+    if name.is_empty() || !LOADED.write().unwrap().insert(name.to_string()) {
+        return None;
+    }
+    let file = completion_dirs().into_iter().find_map(|dir| {
+        [dir.join(name), dir.join(format!("{}.sh", name))]
+            .into_iter()
+            .find(|file| file.is_file())
+    })?;
+    let status = exec::last_status();
+    if let Err(e) = script::source(&file.display().to_string(), &[]).await {
+        println_error!("sh-rs: {}: {}", file.display(), e);
+    }
+    exec::set_last_status(status);
+    get(command).or_else(|| get(name))
+}
+
+/// `~/` 开头的路径换成 `$HOME` 下的路径
+fn expand_home(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("{}/{}", env::var("HOME").unwrap_or_default(), rest),
+        None => path.to_string(),
+    }
+}
+
+/// 以 `prefix` 开头的目录
+fn directories(prefix: &str) -> Vec<String> {
+    paths(prefix, false)
+        .into_iter()
+        .filter(|path| path.ends_with('/'))
+        .collect()
+}
+
+/// 单词末尾的 `$NAME` 或 `${NAME`：返回 `$` 的位置、是否有花括号以及已输入的名称
+fn variable_prefix(word: &str) -> Option<(usize, bool, &str)> {
+    let dollar = word.rfind('$')?;
+    let rest = &word[dollar + 1..];
+    let (braced, name) = match rest.strip_prefix('{') {
+        Some(name) => (true, name),
+        None => (false, rest),
+    };
+    name.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        .then_some((dollar, braced, name))
+}
+
+/// shell 变量与环境变量中以 `prefix` 开头的名称
+fn variables(prefix: &str) -> Vec<String> {
+    let mut names: BTreeSet<String> = var::names().into_iter().collect();
+    names.extend(env::vars_os().filter_map(|(name, _)| name.into_string().ok()));
+    names
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .collect()
+}
+
+/// 以 `prefix` 开头的别名、函数、内置命令与 `PATH` 中的命令
+fn commands(prefix: &str) -> Vec<String> {
+    resolve::command_names()
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .collect()
+}
+
+/// 以 `prefix` 开头的路径，目录以 `/` 结尾；`~` 开头时按 `$HOME` 查找但保留 `~`。
+/// 只有 `prefix` 的文件名部分以 `.` 开头时才列出隐藏文件，`executable` 时只列出目录与可执行文件
+pub fn paths(prefix: &str, executable: bool) -> Vec<String> {
+    if prefix == "~" {
+        return vec!["~/".to_string()];
+    }
+    let (dir, name) = match prefix.rfind('/') {
+        Some(i) => prefix.split_at(i + 1),
+        None => ("", prefix),
+    };
+    let search = if dir.is_empty() {
+        ".".to_string()
+    } else {
+        expand_home(dir)
+    };
+    let Ok(entries) = fs::read_dir(&search) else {
+        return Vec::new();
+    };
+    let mut found: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            if !file_name.starts_with(name) || file_name.starts_with('.') && !name.starts_with('.')
+            {
+                return None;
+            }
+            // 跟随符号链接判断是否是目录
+            let path = Path::new(&search).join(&file_name);
+            if path.is_dir() {
+                Some(format!("{}{}/", dir, file_name))
+            } else if !executable || resolve::is_executable(&path) {
+                Some(format!("{}{}", dir, file_name))
+            } else {
+                None
+            }
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// 按引用方式引用补全结果；`close` 时闭合引号。开头的 `~` 不引用，以便仍然展开
+pub fn quote(value: &str, quoting: Quoting, close: bool) -> String {
+    let (tilde, rest) = match value.find('/') {
+        Some(i) if value.starts_with('~') => value.split_at(i + 1),
+        _ => ("", value),
+    };
+    let quoted = match quoting {
+        Quoting::Raw => return value.to_string(),
+        Quoting::Backslash => {
+            let mut out = String::new();
+            for c in rest.chars() {
+                if SPECIAL_CHARS.contains(c) {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            return format!("{}{}", tilde, out);
+        }
+        Quoting::Single => format!("'{}", rest.replace('\'', "'\\''")),
+        Quoting::Double => {
+            let mut out = String::from("\"");
+            for c in rest.chars() {
+                if matches!(c, '"' | '\\' | '$' | '`') {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out
+        }
+    };
+    let end = if !close {
+        ""
+    } else if quoting == Quoting::Single {
+        "'"
+    } else {
+        "\""
+    };
+    format!("{}{}{}", tilde, quoted, end)
+}
+
+/// 候选项的最长公共前缀
+pub fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut end = first.len();
+    for candidate in &candidates[1..] {
+        end = first
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(end.min(candidate.len()), |((i, _), _)| end.min(i));
+    }
+    first[..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_context() {
+        let context = parse("git com");
+        assert_eq!(context.words, vec!["git", "com"]);
+        assert_eq!(context.start, 4);
+        assert!(!context.command_position);
+        assert!(parse("").command_position);
+        assert!(parse("ls | gr").command_position);
+        assert!(parse("FOO=1 if ec").command_position);
+        assert!(!parse("cat < fi").command_position);
+        let context = parse("cat 'my fi");
+        assert_eq!(context.words, vec!["cat", "my fi"]);
+        assert_eq!(context.quote, Some('\''));
+        assert_eq!(context.start, 4);
+        assert_eq!(parse(r"ls a\ b").words, vec!["ls", "a b"]);
+        assert_eq!(parse("ls ").words, vec!["ls", ""]);
+    }
+
+    #[test]
+    fn test_quote_candidates() {
+        assert_eq!(quote("my file", Quoting::Backslash, true), r"my\ file");
+        assert_eq!(quote("~/a b/", Quoting::Backslash, false), r"~/a\ b/");
+        assert_eq!(quote("it's", Quoting::Single, true), r"'it'\''s'");
+        assert_eq!(quote("~/x y", Quoting::Single, false), "~/'x y");
+        assert_eq!(quote("$a", Quoting::Double, true), "\"\\$a\"");
+        assert_eq!(quote("$HOME", Quoting::Raw, true), "$HOME");
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        let candidates = ["config", "configure", "conf.d/"].map(String::from);
+        assert_eq!(common_prefix(&candidates), "conf");
+        assert_eq!(common_prefix(&candidates[..1]), "config");
+        assert_eq!(common_prefix(&[]), "");
+    }
+
+    #[tokio::test]
+    async fn test_complete_variables() {
+        unsafe { env::set_var("T_COMPLETE_VAR", "1") };
+        let completion = complete("echo \"$T_COMPLETE_V").await;
+        assert_eq!(completion.start, 6);
+        assert_eq!(completion.word, "$T_COMPLETE_V");
+        assert_eq!(completion.candidates, vec!["$T_COMPLETE_VAR"]);
+        let completion = complete("echo ${T_COMPLETE_V").await;
+        assert_eq!(completion.candidates, vec!["${T_COMPLETE_VAR}"]);
+        assert!(complete("echo '$T_COMPLETE_V").await.candidates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_call_function_keeps_variables() {
+        exec::execute_line("_t_complete() { COMPREPLY=(\"$COMP_CWORD\" \"$2\"); }")
+            .await
+            .unwrap();
+        unsafe { env::set_var("COMP_LINE", "exported") };
+        var::set_scalar("COMPREPLY", "mine", false);
+        let reply = call_function("_t_complete", &parse("cmd ar"), "cmd ar").await;
+        assert_eq!(reply, vec!["1", "ar"]);
+        assert_eq!(env::var("COMP_LINE").as_deref(), Ok("exported"));
+        assert_eq!(var::values("COMPREPLY"), vec!["mine"]);
+        assert!(!var::is_set("COMP_WORDS"));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::builtin::cd;
+use lazy_static::lazy_static;
+use std::sync::RwLock;
+
+lazy_static! {
+    /// `pushd` 保存的目录，不含当前目录
+    static ref SAVED: RwLock<Vec<String>> = RwLock::new(Vec::new());
+}
+
+/// 完整的目录栈，下标 0 为当前目录
+pub fn entries() -> Vec<String> {
+    let mut entries = vec![cd::logical_pwd().display().to_string()];
+    entries.extend(SAVED.read().unwrap().iter().cloned());
+    entries
+}
+
+/// 以完整目录栈替换保存的目录，下标 0 的当前目录被忽略
+pub fn replace(entries: Vec<String>) {
+    *SAVED.write().unwrap() = entries.into_iter().skip(1).collect();
+}
+
+pub fn clear() {
+    SAVED.write().unwrap().clear();
+}
+
+/// `+N` 从左数、`-N` 从右数，不带符号时同 `+N`；超出范围时返回 `None`
+pub fn resolve_index(spec: &str, len: usize) -> Option<usize> {
+    let (from_right, digits) = match spec.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, spec.strip_prefix('+').unwrap_or(spec)),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: usize = digits.parse().ok()?;
+    if n >= len {
+        return None;
+    }
+    Some(if from_right { len - 1 - n } else { n })
+}
+
+/// `~N`、`~+N`、`~-N` 引用的目录
+pub fn get(spec: &str) -> Option<String> {
+    let entries = entries();
+    let index = resolve_index(spec, entries.len())?;
+    entries.into_iter().nth(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirstack_resolve_index() {
+        assert_eq!(resolve_index("0", 3), Some(0));
+        assert_eq!(resolve_index("+2", 3), Some(2));
+        assert_eq!(resolve_index("-0", 3), Some(2));
+        assert_eq!(resolve_index("-2", 3), Some(0));
+        assert_eq!(resolve_index("+3", 3), None);
+        assert_eq!(resolve_index("-3", 3), None);
+        assert_eq!(resolve_index("+", 3), None);
+        assert_eq!(resolve_index("1a", 3), None);
+    }
+}
@@ -1,60 +1,304 @@
-use crate::token::{CommandPart, ExecutionSource, PipeEndpoint};
-use crate::{Result, println_error};
-use std::env;
+use crate::builtin::{self, cd};
+use crate::token::{
+    self, AssignValue, Assignment, CommandPart, Connector, ExecutionSource, List, PipeEndpoint,
+    expand, parse_command_chain, parse_program,
+};
+use crate::{Result, options, println_error, resolve, script, suggest, var};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
 use std::fs::File;
-use std::process::Command;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Arc, RwLock};
 
-pub(crate) async fn execute_command_parts(parts: Vec<CommandPart>) -> Result<()> {
+/// 最近一条命令的退出状态，即 `$?`
+static LAST_STATUS: AtomicI32 = AtomicI32::new(0);
+
+pub(crate) fn last_status() -> i32 {
+    LAST_STATUS.load(Ordering::SeqCst)
+}
+
+pub(crate) fn set_last_status(status: i32) {
+    LAST_STATUS.store(status, Ordering::SeqCst);
+}
+
+lazy_static! {
+    /// 已定义的 shell 函数
+    static ref FUNCTIONS: RwLock<HashMap<String, Arc<List>>> = RwLock::new(HashMap::new());
+}
+
+/// `return` 之后跳过当前函数或 source 文件中剩余的命令
+static RETURNING: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn request_return() {
+    RETURNING.store(true, Ordering::SeqCst);
+}
+
+pub(crate) fn returning() -> bool {
+    RETURNING.load(Ordering::SeqCst)
+}
+
+/// 清除 `return` 标记，返回之前是否处于返回状态
+pub(crate) fn take_return() -> bool {
+    RETURNING.swap(false, Ordering::SeqCst)
+}
+
+pub(crate) fn function(name: &str) -> Option<Arc<List>> {
+    FUNCTIONS.read().unwrap().get(name).cloned()
+}
+
+/// 删除函数，返回函数是否存在
+pub(crate) fn remove_function(name: &str) -> bool {
+    FUNCTIONS.write().unwrap().remove(name).is_some()
+}
+
+/// 已定义的函数名，按名称排序
+pub(crate) fn function_names() -> Vec<String> {
+    let mut names: Vec<String> = FUNCTIONS.read().unwrap().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+/// 以 `args` 作为位置参数调用函数，返回函数的退出状态
+async fn call_function(body: &List, args: Vec<String>) -> i32 {
+    let saved = var::replace_positional_args(args);
+    Box::pin(execute_list(body)).await;
+    take_return();
+    var::replace_positional_args(saved);
+    last_status()
+}
+
+/// 将进程退出状态转换为 shell 状态码，被信号终止时为 128 + 信号值
+fn status_code(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status
+        .code()
+        .or_else(|| status.signal().map(|sig| 128 + sig))
+        .unwrap_or(1)
+}
+
+/// 解析并执行一段完整的输入，解析失败时 `$?` 为 2
+pub(crate) async fn execute_line(line: &str) -> Result<()> {
+    match parse_program(line) {
+        Ok(list) => {
+            execute_list(&list).await;
+            Ok(())
+        }
+        Err(e) => {
+            set_last_status(2);
+            Err(e.into())
+        }
+    }
+}
+
+/// 依次执行命令列表，`&&` 与 `||` 根据前一条命令的状态决定是否执行
+pub(crate) async fn execute_list(list: &List) {
+    for (connector, command) in list {
+        let run = match connector {
+            Connector::Seq => true,
+            Connector::And => last_status() == 0,
+            Connector::Or => last_status() != 0,
+        };
+        if run {
+            execute_command(command).await;
+        }
+        if returning() {
+            break;
+        }
+    }
+}
+
+async fn execute_command(command: &token::Command) {
+    match command {
+        token::Command::Simple(tokens) => match parse_command_chain(expand(tokens.clone())) {
+            Ok(parts) => {
+                if let Err(e) = execute_command_parts(parts).await {
+                    println_error!("{}Execution error: {}", script::error_prefix(), e);
+                }
+            }
+            Err(e) => {
+                set_last_status(2);
+                println_error!("{}{}", script::error_prefix(), e);
+            }
+        },
+        token::Command::If {
+            branches,
+            otherwise,
+        } => {
+            for (condition, body) in branches {
+                Box::pin(execute_list(condition)).await;
+                if returning() {
+                    return;
+                }
+                if last_status() == 0 {
+                    Box::pin(execute_list(body)).await;
+                    return;
+                }
+            }
+            match otherwise {
+                Some(body) => Box::pin(execute_list(body)).await,
+                None => set_last_status(0),
+            }
+        }
+        token::Command::Group(body) => Box::pin(execute_list(body)).await,
+        token::Command::Function { name, body } => {
+            FUNCTIONS
+                .write()
+                .unwrap()
+                .insert(name.clone(), body.clone());
+            set_last_status(0);
+        }
+    }
+}
+
+/// 在当前 shell 中执行一条赋值
+fn assign(assignment: &Assignment) -> std::result::Result<(), String> {
+    let Assignment {
+        name,
+        index,
+        append,
+        value,
+    } = assignment;
+    match (index, value) {
+        (None, AssignValue::Scalar(value)) => {
+            var::set_scalar(name, value, *append);
+            Ok(())
+        }
+        (Some(index), AssignValue::Scalar(value)) => var::set_element(name, index, value, *append),
+        (None, AssignValue::Array(elements)) => var::set_array(name, elements, *append),
+        (Some(_), AssignValue::Array(_)) => {
+            Err(format!("{}: cannot assign list to array member", name))
+        }
+    }
+}
+
+pub(crate) async fn execute_command_parts(mut parts: Vec<CommandPart>) -> Result<()> {
     if parts.is_empty() {
         return Ok(());
     }
 
-    // 检查内置命令 (只能是第一个命令)
-    let CommandPart::Execute { name, args, .. } = &parts[0];
-    match name.as_str() {
-        "exit" => std::process::exit(0),
-        "cd" => {
-            let home_path = env::var("HOME").unwrap_or_else(|_| "/".to_string());
-            let path = args.get(0).unwrap_or(&home_path);
-            let new_dir = std::path::Path::new(path);
-            if let Err(e) = env::set_current_dir(new_dir) {
-                println_error!("cd error: {}", e);
+    // `command name args` 跳过函数，直接执行内置命令或外部命令
+    let skip_functions = strip_command_prefix(&mut parts[0]);
+    let lookup_function = |name: &str| {
+        if skip_functions { None } else { function(name) }
+    };
+
+    // 仅有赋值的命令在当前 shell 中设置变量
+    let CommandPart::Execute {
+        name,
+        args,
+        assignments,
+        ..
+    } = &parts[0];
+    if name.is_empty() {
+        let mut status = 0;
+        for assignment in assignments {
+            if let Err(e) = assign(assignment) {
+                println_error!("{}", e);
+                status = 1;
             }
-            return Ok(());
         }
-        _ => {}
+        set_last_status(status);
+        return Ok(());
+    }
+
+    // autocd：单独的目录名且没有同名命令时进入该目录
+    if parts.len() == 1
+        && args.is_empty()
+        && lookup_function(name).is_none()
+        && !builtin::is_builtin(name)
+        && let Some(dir) = autocd_target(name)
+    {
+        let status = match cd::change_dir(&dir, false) {
+            Ok(_) => 0,
+            Err(e) => {
+                println_error!("cd: {}", e);
+                1
+            }
+        };
+        set_last_status(status);
+        return Ok(());
     }
 
-    let mut previous_stdout_handle: Option<std::process::ChildStdout> = None;
+    // 函数与内置命令在当前 shell 中执行，输出重定向到文件或管道时临时替换 fd 1
+    let mut previous_stdout_handle: Option<Stdio> = None;
+    let mut parts = parts.into_iter().peekable();
+    if let Some(CommandPart::Execute {
+        name, args, stdout, ..
+    }) = parts.peek()
+        && (lookup_function(name).is_some() || builtin::is_builtin(name))
+    {
+        let target = match stdout {
+            ExecutionSource::File(path) => Some(open_output(path)?),
+            ExecutionSource::Pipe(PipeEndpoint::Write) => Some(temporary_file()?),
+            _ => None,
+        };
+        let redirect = target.as_ref().map(StdoutRedirect::new).transpose()?;
+        let status = match lookup_function(name) {
+            Some(body) => call_function(&body, args.clone()).await,
+            None => builtin::try_execute(name, args).await.unwrap_or(0),
+        };
+        drop(redirect);
+        set_last_status(status);
+        if let (ExecutionSource::Pipe(PipeEndpoint::Write), Some(mut file)) = (stdout, target) {
+            file.seek(SeekFrom::Start(0))?;
+            previous_stdout_handle = Some(Stdio::from(file));
+        }
+        parts.next();
+    }
 
     // 遍历执行命令链
-    for part in parts.into_iter() {
+    for part in parts {
         let CommandPart::Execute {
             name,
             args,
+            assignments,
             stdin,
             stdout,
         } = part;
 
-        let mut command = Command::new(&name);
-        command.args(&args);
+        // 经过缓存解析命令路径，argv[0] 保持为命令名
+        let Some(path) = resolve::find_command(&name) else {
+            let not_found = io::Error::from(io::ErrorKind::NotFound);
+            let status = command_failed(&name, &args, &not_found).await;
+            if matches!(stdout, ExecutionSource::Pipe(PipeEndpoint::Write)) {
+                previous_stdout_handle = Some(Stdio::null());
+            } else {
+                set_last_status(status);
+            }
+            continue;
+        };
+        let mut command = Command::new(path);
+        command.arg0(&name).args(&args);
+        // 命令前的标量赋值只作用于该命令的环境
+        for assignment in assignments {
+            if let AssignValue::Scalar(value) = assignment.value
+                && assignment.index.is_none()
+            {
+                command.env(assignment.name, value);
+            }
+        }
 
         // --- 设置 STDIN ---
         match stdin {
             ExecutionSource::Inherit => {
-                command.stdin(std::process::Stdio::inherit());
+                command.stdin(Stdio::inherit());
             }
             ExecutionSource::Pipe(PipeEndpoint::Read) => {
                 if let Some(handle) = previous_stdout_handle.take() {
                     command.stdin(handle);
                 } else {
                     // 错误情况：管道没有上游，应继承 stdin
-                    command.stdin(std::process::Stdio::inherit());
+                    command.stdin(Stdio::inherit());
                 }
             }
             ExecutionSource::File(path) => {
                 let file = File::open(path)?;
-                command.stdin(std::process::Stdio::from(file));
+                command.stdin(Stdio::from(file));
             }
             _ => {}
         }
@@ -62,40 +306,206 @@ pub(crate) async fn execute_command_parts(parts: Vec<CommandPart>) -> Result<()>
         // --- 设置 STDOUT ---
         let is_piped = match stdout {
             ExecutionSource::Inherit => {
-                command.stdout(std::process::Stdio::inherit());
+                command.stdout(Stdio::inherit());
                 false
             }
             ExecutionSource::Pipe(PipeEndpoint::Write) => {
-                command.stdout(std::process::Stdio::piped());
+                command.stdout(Stdio::piped());
                 true
             }
             ExecutionSource::File(path) => {
-                let file = if path.starts_with(">>") {
-                    // 追加重定向
-                    File::options().append(true).create(true).open(&path[2..])?
-                } else {
-                    // 覆盖重定向
-                    File::create(path)?
-                };
-                command.stdout(std::process::Stdio::from(file));
+                command.stdout(Stdio::from(open_output(&path)?));
                 false
             }
             _ => false,
         };
 
         // --- 执行 ---
-        let mut child = command.spawn()?;
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                let status = command_failed(&name, &args, &e).await;
+                // 管道中后面的命令读到的是空输入
+                if is_piped {
+                    previous_stdout_handle = Some(Stdio::null());
+                } else {
+                    set_last_status(status);
+                }
+                continue;
+            }
+        };
 
         // --- 存储管道句柄 或 等待完成 ---
         if is_piped {
             // 如果是管道输出，保存输出句柄给下一个命令
-            previous_stdout_handle = child.stdout.take();
+            previous_stdout_handle = child.stdout.take().map(Stdio::from);
         } else if previous_stdout_handle.is_none() {
             // 如果不是管道输出，且没有未连接的管道 (即是链条的终点或单个命令)
-            child.wait()?;
+            set_last_status(status_code(child.wait()?));
         }
         // 否则，如果是链条终点，但前面还有未等待的命令，我们只等待链条的最后一个
     }
 
     Ok(())
 }
+
+/// 打开 autocd 选项时命令名对应的目录：`...` 表示 `../..`，每多一个点多上一级
+fn autocd_target(name: &str) -> Option<String> {
+    if !options::is_set("autocd") {
+        return None;
+    }
+    autocd_dir(Path::new("."), name)
+}
+
+/// 相对于 `base` 存在的目录名，且没有同名命令
+fn autocd_dir(base: &Path, name: &str) -> Option<String> {
+    let dir = if name.len() > 2 && name.bytes().all(|b| b == b'.') {
+        vec![".."; name.len() - 1].join("/")
+    } else {
+        name.to_string()
+    };
+    if !base.join(&dir).is_dir() || (!name.contains('/') && resolve::search_path(name).is_some()) {
+        return None;
+    }
+    Some(dir)
+}
+
+/// 命令无法启动：找不到时调用 `command_not_found_handle` 函数或给出相近的命令，
+/// 返回 127；存在但无法执行时返回 126
+async fn command_failed(name: &str, args: &[String], error: &io::Error) -> i32 {
+    let prefix = script::error_prefix();
+    if error.kind() != io::ErrorKind::NotFound {
+        let reason = if Path::new(name).is_dir() {
+            "Is a directory".to_string()
+        } else if error.kind() == io::ErrorKind::PermissionDenied {
+            "Permission denied".to_string()
+        } else {
+            error.to_string()
+        };
+        println_error!("{}{}: {}", prefix, name, reason);
+        return 126;
+    }
+    if name.contains('/') {
+        println_error!("{}{}: No such file or directory", prefix, name);
+        return 127;
+    }
+    if let Some(handler) = function("command_not_found_handle") {
+        let mut handler_args = vec![name.to_string()];
+        handler_args.extend_from_slice(args);
+        return call_function(&handler, handler_args).await;
+    }
+    println_error!("{}{}: command not found", prefix, name);
+    let suggestions = suggest::suggestions(name);
+    if !suggestions.is_empty() {
+        println_error!("Did you mean: {}?", suggestions.join(", "));
+    }
+    127
+}
+
+/// 去掉 `command [-p] [--]` 前缀，返回是否去掉了前缀；`command -v` 等交给内置命令处理
+fn strip_command_prefix(part: &mut CommandPart) -> bool {
+    let CommandPart::Execute { name, args, .. } = part;
+    if name != "command" {
+        return false;
+    }
+    let mut skip = 0;
+    while let Some(arg) = args.get(skip) {
+        match arg.as_str() {
+            "-p" => skip += 1,
+            "--" => {
+                skip += 1;
+                break;
+            }
+            _ => break,
+        }
+    }
+    match args.get(skip) {
+        Some(arg) if !arg.starts_with('-') => {
+            args.drain(..skip);
+            *name = args.remove(0);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// 打开输出重定向的目标文件，`>>` 前缀表示追加
+fn open_output(path: &str) -> io::Result<File> {
+    match path.strip_prefix(">>") {
+        Some(path) => File::options().append(true).create(true).open(path),
+        None => File::create(path),
+    }
+}
+
+/// 匿名临时文件，暂存内置命令写往管道的输出
+fn temporary_file() -> io::Result<File> {
+    static COUNTER: AtomicI32 = AtomicI32::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "sh-rs-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    std::fs::remove_file(&path)?;
+    Ok(file)
+}
+
+/// 在存活期间将标准输出指向另一个文件，释放时恢复
+struct StdoutRedirect {
+    saved: i32,
+}
+
+impl StdoutRedirect {
+    fn new(target: &File) -> io::Result<Self> {
+        io::stdout().flush()?;
+        let saved = unsafe { libc::dup(1) };
+        if saved < 0 || unsafe { libc::dup2(target.as_raw_fd(), 1) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(StdoutRedirect { saved })
+    }
+}
+
+impl Drop for StdoutRedirect {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        unsafe {
+            libc::dup2(self.saved, 1);
+            libc::close(self.saved);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_autocd_target() {
+        let base = std::env::temp_dir().join(format!("sh-rs-autocd-{}", std::process::id()));
+        let nested = base.join("a/b/c");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(base.join("sh")).unwrap();
+
+        assert_eq!(autocd_dir(&nested, "..").as_deref(), Some(".."));
+        assert_eq!(autocd_dir(&nested, "...").as_deref(), Some("../.."));
+        assert_eq!(autocd_dir(&nested, "....").as_deref(), Some("../../.."));
+        assert_eq!(autocd_dir(&base, "a").as_deref(), Some("a"));
+        assert_eq!(autocd_dir(&base, "a/b").as_deref(), Some("a/b"));
+        assert_eq!(autocd_dir(&base, "missing"), None);
+        // 与 PATH 中的命令同名时执行命令
+        assert_eq!(autocd_dir(&base, "sh"), None);
+
+        // 关闭 autocd 时不进入目录
+        assert_eq!(autocd_target(".."), None);
+        options::set("autocd", true).unwrap();
+        assert_eq!(autocd_target("src").as_deref(), Some("src"));
+        options::set("autocd", false).unwrap();
+        assert_eq!(autocd_target("src"), None);
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+use crate::script;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 所有目录的访问次数之和超过该值时整体衰减，旧目录逐渐被遗忘
+const MAX_TOTAL_RANK: f64 = 9000.0;
+
+/// 数据库中的一个目录：访问次数与最近一次访问的时间戳
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: String,
+    pub rank: f64,
+    pub time: u64,
+}
+
+impl Entry {
+    /// 频率按最近一次访问的时间加权：一小时内 ×4，一天内 ×2，一周内 ÷2，更早 ÷4
+    pub fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.time);
+        if age < 3600 {
+            self.rank * 4.0
+        } else if age < 86400 {
+            self.rank * 2.0
+        } else if age < 604800 {
+            self.rank / 2.0
+        } else {
+            self.rank / 4.0
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// 数据库与 `~/.sh_history` 放在同一目录下
+fn data_path() -> Option<PathBuf> {
+    env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .ok()
+        .map(|home| Path::new(&home).join(".sh_z"))
+}
+
+/// 每行 `path|rank|time`，无法解析的行被忽略
+pub fn load() -> Vec<Entry> {
+    let Some(contents) = data_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.rsplitn(3, '|');
+            let time = fields.next()?.parse().ok()?;
+            let rank = fields.next()?.parse().ok()?;
+            let path = fields.next()?.to_string();
+            Some(Entry { path, rank, time })
+        })
+        .collect()
+}
+
+/// 先写入临时文件再改名，避免多个 shell 同时写入时损坏数据库
+fn store(entries: &[Entry]) -> std::io::Result<()> {
+    let Some(path) = data_path() else {
+        return Ok(());
+    };
+    let temporary = path.with_extension(format!("{}", std::process::id()));
+    let mut file = fs::File::create(&temporary)?;
+    for entry in entries {
+        writeln!(file, "{}|{}|{}", entry.path, entry.rank, entry.time)?;
+    }
+    fs::rename(temporary, path)
+}
+
+/// 在 `~/.sh_z.lock` 上加排他锁期间读取、修改并写回数据库，
+/// 多个 shell 同时更新时不会丢失彼此的修改；锁文件无法打开时不加锁
+fn update<T>(modify: impl FnOnce(&mut Vec<Entry>) -> Option<T>) -> Option<T> {
+    let lock = data_path().and_then(|path| {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.with_extension("lock"))
+            .ok()
+    });
+    if let Some(lock) = &lock {
+        unsafe {
+            libc::flock(lock.as_raw_fd(), libc::LOCK_EX);
+        }
+    }
+    let mut entries = load();
+    let result = modify(&mut entries);
+    if result.is_some() {
+        let _ = store(&entries);
+    }
+    // 关闭锁文件时释放锁
+    result
+}
+
+/// 交互式 shell 启动时开启记录，脚本与 `-c` 中不记录
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// 记录一次在提示符下输入的命令对目录的访问；未开启记录、正在执行脚本或 `source` 的文件时
+/// 不记录，家目录也不记录
+pub fn add(dir: &Path) {
+    if !ENABLED.load(Ordering::SeqCst) || script::is_running() {
+        return;
+    }
+    let path = dir.display().to_string();
+    if env::var("HOME").is_ok_and(|home| home == path) || path.contains('\n') {
+        return;
+    }
+    update(|entries| {
+        record(entries, path, now());
+        Some(())
+    });
+}
+
+fn record(entries: &mut Vec<Entry>, path: String, now: u64) {
+    match entries.iter_mut().find(|entry| entry.path == path) {
+        Some(entry) => {
+            entry.rank += 1.0;
+            entry.time = now;
+        }
+        None => entries.push(Entry {
+            path,
+            rank: 1.0,
+            time: now,
+        }),
+    }
+    age(entries);
+}
+
+/// 删除目录的记录，返回它之前是否存在
+pub fn remove(dir: &str) -> bool {
+    update(|entries| {
+        let before = entries.len();
+        entries.retain(|entry| entry.path != dir);
+        (entries.len() != before).then_some(())
+    })
+    .is_some()
+}
+
+fn age(entries: &mut Vec<Entry>) {
+    let total: f64 = entries.iter().map(|entry| entry.rank).sum();
+    if total > MAX_TOTAL_RANK {
+        for entry in entries.iter_mut() {
+            entry.rank *= 0.99;
+        }
+        entries.retain(|entry| entry.rank >= 1.0);
+    }
+}
+
+/// 片段按顺序出现在路径中，且最后一个片段出现在最后一级目录名中；
+/// 片段全为小写时忽略大小写
+pub fn matches(path: &str, fragments: &[String]) -> bool {
+    let ignore_case = fragments
+        .iter()
+        .all(|fragment| !fragment.chars().any(char::is_uppercase));
+    let path = if ignore_case {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    };
+    let mut rest = path.as_str();
+    for fragment in fragments {
+        match rest.find(fragment.as_str()) {
+            Some(i) => rest = &rest[i + fragment.len()..],
+            None => return false,
+        }
+    }
+    match fragments.last() {
+        Some(last) => {
+            let name = path.rsplit('/').next().unwrap_or_default();
+            name.contains(last.as_str()) || last.contains('/')
+        }
+        None => true,
+    }
+}
+
+/// 匹配片段且仍然存在的目录，按得分从高到低排序
+pub fn query(fragments: &[String]) -> Vec<(f64, String)> {
+    let now = now();
+    let mut found: Vec<(f64, String)> = load()
+        .into_iter()
+        .filter(|entry| matches(&entry.path, fragments) && Path::new(&entry.path).is_dir())
+        .map(|entry| (entry.score(now), entry.path))
+        .collect();
+    found.sort_by(|a, b| b.0.total_cmp(&a.0));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frecency_score() {
+        let entry = Entry {
+            path: "/tmp".to_string(),
+            rank: 8.0,
+            time: 1_000_000,
+        };
+        assert_eq!(entry.score(1_000_000 + 60), 32.0);
+        assert_eq!(entry.score(1_000_000 + 7200), 16.0);
+        assert_eq!(entry.score(1_000_000 + 3 * 86400), 4.0);
+        assert_eq!(entry.score(1_000_000 + 30 * 86400), 2.0);
+    }
+
+    #[test]
+    fn test_frecency_record() {
+        let mut entries = Vec::new();
+        record(&mut entries, "/tmp".to_string(), 10);
+        record(&mut entries, "/tmp".to_string(), 20);
+        record(&mut entries, "/usr".to_string(), 30);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].rank, entries[0].time), (2.0, 20));
+    }
+
+    #[test]
+    fn test_frecency_matches() {
+        let fragments = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(matches("/home/me/src/sh-rs", &fragments(&["sh"])));
+        assert!(matches("/home/me/src/sh-rs", &fragments(&["src", "rs"])));
+        assert!(matches(
+            "/home/me/Projects/Shell",
+            &fragments(&["proj", "shell"])
+        ));
+        // 顺序不对、最后一个片段不在最后一级目录名中、大写片段区分大小写
+        assert!(!matches("/home/me/src/sh-rs", &fragments(&["rs", "src"])));
+        assert!(!matches("/home/me/src/sh-rs", &fragments(&["src"])));
+        assert!(!matches(
+            "/home/me/Projects/Shell",
+            &fragments(&["Proj", "shell"])
+        ));
+    }
+}
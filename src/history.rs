@@ -1,6 +1,6 @@
 use crate::Result;
 use lazy_static::lazy_static;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 
 lazy_static! {
@@ -12,18 +12,11 @@ pub struct History;
 impl History {
     pub async fn load() -> Result<()> {
         let mut history = HISTORY.lock().await;
-        match std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")) {
-            Ok(home) => {
-                let history_path = format!("{}/.sh_history", home);
-                if let Ok(file) = tokio::fs::File::open(&history_path).await {
-                    let reader = tokio::io::BufReader::new(file);
-                    let mut lines = reader.lines();
-                    while let Ok(Some(line)) = lines.next_line().await {
-                        history.push(line);
-                    }
-                }
+        if let Ok(home) = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")) {
+            let history_path = format!("{}/.sh_history", home);
+            if let Ok(contents) = tokio::fs::read_to_string(&history_path).await {
+                history.extend(parse_entries(&contents));
             }
-            Err(_) => {}
         }
         Ok(())
     }
@@ -39,13 +32,19 @@ impl History {
                     .append(true)
                     .open(&history_path)
                     .await?;
-                file.write_all(format!("{}\n", command).as_bytes()).await?;
+                file.write_all(format!("{}\n", escape_newlines(command)).as_bytes())
+                    .await?;
                 Ok(())
             }
             Err(e) => Err(Box::new(e)),
         }
     }
 
+    /// 全部历史命令，从旧到新
+    pub async fn all() -> Vec<String> {
+        HISTORY.lock().await.clone()
+    }
+
     /// 倒序获取历史命令，0 为最新的命令
     pub async fn get_by_index(index: usize) -> Option<String> {
         let history = HISTORY.lock().await;
@@ -56,3 +55,54 @@ impl History {
         }
     }
 }
+
+/// 多行命令在历史文件中的续行标记：除最后一行外，每行末尾加上一个 NUL。
+/// 输入的命令中不会有 NUL，因此以反斜杠等任何字符结尾的单行命令（包括之前版本写入的）都不会被误认为续行
+const CONTINUATION: char = '\0';
+
+fn escape_newlines(command: &str) -> String {
+    command.replace('\n', &format!("{}\n", CONTINUATION))
+}
+
+/// 读取历史文件：以续行标记结尾的行与下一行属于同一条多行命令
+fn parse_entries(contents: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut entry = String::new();
+    for line in contents.lines() {
+        match line.strip_suffix(CONTINUATION) {
+            Some(line) => {
+                entry.push_str(line);
+                entry.push('\n');
+            }
+            None => {
+                entry.push_str(line);
+                entries.push(std::mem::take(&mut entry));
+            }
+        }
+    }
+    if !entry.is_empty() {
+        entries.push(entry);
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_multi_line() {
+        let commands = ["echo \"a\nb\"", "echo a \\\nb", "echo 'a\\'", "ls"];
+        let contents: String = commands
+            .iter()
+            .map(|command| format!("{}\n", escape_newlines(command)))
+            .collect();
+        assert_eq!(
+            contents,
+            "echo \"a\0\nb\"\necho a \\\0\nb\necho 'a\\'\nls\n"
+        );
+        assert_eq!(parse_entries(&contents), commands);
+        // 之前版本写入的以反斜杠结尾的行仍是单独的命令
+        assert_eq!(parse_entries("echo a\\\nls\n"), ["echo a\\", "ls"]);
+    }
+}
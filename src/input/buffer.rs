@@ -0,0 +1,289 @@
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// `index` 处或之前最近的字素边界，超出文本时是文本的末尾
+pub fn boundary_at_or_before(text: &str, index: usize) -> usize {
+    if index >= text.len() {
+        return text.len();
+    }
+    text.grapheme_indices(true)
+        .map(|(i, _)| i)
+        .take_while(|&i| i <= index)
+        .last()
+        .unwrap_or(0)
+}
+
+/// 行编辑器的文本与光标。光标是字节下标，并且总是落在字素簇（grapheme cluster）的边界上，
+/// 因此组合字符、CJK 字符与 emoji 都作为一个整体移动和删除
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LineBuffer {
+    text: String,
+    cursor: usize,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// 替换全部文本，光标移到行尾
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    /// 替换全部文本并把光标放到 `cursor`，`cursor` 会被调整到字素边界
+    pub fn set_with_cursor(&mut self, text: String, cursor: usize) {
+        self.text = text;
+        self.cursor = boundary_at_or_before(&self.text, cursor);
+    }
+
+    pub fn before_cursor(&self) -> &str {
+        &self.text[..self.cursor]
+    }
+
+    pub fn after_cursor(&self) -> &str {
+        &self.text[self.cursor..]
+    }
+
+    pub fn insert(&mut self, s: &str) {
+        self.text.insert_str(self.cursor, s);
+        self.cursor += s.len();
+    }
+
+    /// 光标前一个字素簇的起始位置
+    fn prev_boundary(&self) -> Option<usize> {
+        self.before_cursor()
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(i, _)| i)
+    }
+
+    /// 光标后一个字素簇的结束位置
+    fn next_boundary(&self) -> Option<usize> {
+        self.after_cursor()
+            .graphemes(true)
+            .next()
+            .map(|g| self.cursor + g.len())
+    }
+
+    pub fn move_left(&mut self) -> bool {
+        match self.prev_boundary() {
+            Some(i) => {
+                self.cursor = i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn move_right(&mut self) -> bool {
+        match self.next_boundary() {
+            Some(i) => {
+                self.cursor = i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 把光标移到 `cursor`，`cursor` 须是字素边界
+    pub fn set_cursor(&mut self, cursor: usize) {
+        self.cursor = cursor.min(self.text.len());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// 删除光标前的一个字素簇
+    pub fn backspace(&mut self) -> bool {
+        match self.prev_boundary() {
+            Some(start) => {
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 删除光标处的一个字素簇
+    pub fn delete(&mut self) -> bool {
+        match self.next_boundary() {
+            Some(end) => {
+                self.text.replace_range(self.cursor..end, "");
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 删除 `start..end` 并把光标放到 `start`，返回删除的文本
+    pub fn remove_range(&mut self, start: usize, end: usize) -> String {
+        let removed = self.text[start..end].to_string();
+        self.text.replace_range(start..end, "");
+        self.cursor = start;
+        removed
+    }
+
+    /// 光标之后下一个单词的结尾，`is_word` 判断字素簇是否属于单词
+    pub fn word_end_after(&self, is_word: fn(&str) -> bool) -> usize {
+        let mut seen_word = false;
+        for (i, grapheme) in self.after_cursor().grapheme_indices(true) {
+            if is_word(grapheme) {
+                seen_word = true;
+            } else if seen_word {
+                return self.cursor + i;
+            }
+        }
+        self.text.len()
+    }
+
+    /// 光标之前最近一个单词的开头
+    pub fn word_start_before(&self, is_word: fn(&str) -> bool) -> usize {
+        let mut seen_word = false;
+        for (i, grapheme) in self.before_cursor().grapheme_indices(true).rev() {
+            if is_word(grapheme) {
+                seen_word = true;
+            } else if seen_word {
+                return i + grapheme.len();
+            }
+        }
+        0
+    }
+
+    /// 交换光标前后的两个字素簇并右移光标；在行尾时交换最后两个
+    pub fn transpose(&mut self) -> bool {
+        if self.cursor == self.text.len() && !self.move_left() {
+            return false;
+        }
+        let (Some(start), Some(end)) = (self.prev_boundary(), self.next_boundary()) else {
+            return false;
+        };
+        let left = self.text[start..self.cursor].to_string();
+        let right = self.text[self.cursor..end].to_string();
+        self.text.replace_range(start..end, &(right + &left));
+        self.cursor = end;
+        true
+    }
+
+    /// 光标之前文本的显示宽度（终端列数）
+    pub fn width_before_cursor(&self) -> usize {
+        display_width(self.before_cursor())
+    }
+
+    pub fn width(&self) -> usize {
+        display_width(&self.text)
+    }
+}
+
+/// Emacs 风格的单词：字母与数字（包括 CJK 字符）
+pub fn is_alphanumeric(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(char::is_alphanumeric)
+}
+
+/// `Ctrl-W` 使用的单词：以空白分隔
+pub fn is_not_whitespace(grapheme: &str) -> bool {
+    !grapheme.chars().all(char::is_whitespace)
+}
+
+/// 字符串在终端中占用的列数，CJK 等宽字符占两列
+pub fn display_width(s: &str) -> usize {
+    s.split('\n').map(|line| line.width()).sum::<usize>() + s.matches('\n').count() * 2
+}
+
+/// 多行的历史命令中的换行显示为 `^J`，与 [`display_width`] 的计算一致
+pub fn visible(s: &str) -> Cow<'_, str> {
+    if s.contains('\n') {
+        Cow::Owned(s.replace('\n', "^J"))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_graphemes() {
+        let mut buffer = LineBuffer::new();
+        buffer.insert("cd 文件é👍🏽");
+        assert_eq!(buffer.width(), 3 + 4 + 1 + 2);
+        assert!(buffer.move_left());
+        assert_eq!(buffer.after_cursor(), "👍🏽");
+        assert!(buffer.backspace());
+        assert_eq!(buffer.text(), "cd 文件👍🏽");
+        buffer.move_left();
+        assert_eq!(buffer.width_before_cursor(), 5);
+        assert_eq!(display_width("a\nb"), display_width(&visible("a\nb")));
+        buffer.insert("档");
+        assert_eq!(buffer.text(), "cd 文档件👍🏽");
+        buffer.move_end();
+        assert!(!buffer.move_right());
+        buffer.set_with_cursor(buffer.text().to_string(), 0);
+        assert!(!buffer.backspace());
+        assert!(!buffer.move_left());
+    }
+
+    #[test]
+    fn test_buffer_words_and_transpose() {
+        let mut buffer = LineBuffer::new();
+        buffer.insert("git commit -m 提交说明");
+        assert_eq!(
+            buffer.word_start_before(is_alphanumeric),
+            "git commit -m ".len()
+        );
+        buffer.set_with_cursor(buffer.text().to_string(), "git commit -".len());
+        assert_eq!(
+            buffer.word_start_before(is_not_whitespace),
+            "git commit ".len()
+        );
+        assert_eq!(buffer.word_start_before(is_alphanumeric), "git ".len());
+        assert_eq!(
+            buffer.word_end_after(is_alphanumeric),
+            "git commit -m".len()
+        );
+        let killed = buffer.remove_range(4, buffer.cursor());
+        assert_eq!(killed, "commit -");
+        assert_eq!(buffer.text(), "git m 提交说明");
+
+        buffer.set("ab文");
+        assert!(buffer.transpose());
+        assert_eq!(buffer.text(), "a文b");
+        buffer.set_with_cursor("ab文".to_string(), 1);
+        assert!(buffer.transpose());
+        assert_eq!((buffer.text(), buffer.cursor()), ("ba文", 2));
+        buffer.move_home();
+        assert!(!buffer.transpose());
+    }
+
+    #[test]
+    fn test_buffer_combining_marks() {
+        let mut buffer = LineBuffer::new();
+        buffer.insert("e\u{301}x");
+        buffer.move_left();
+        buffer.move_left();
+        assert_eq!(buffer.cursor(), 0);
+        buffer.set_with_cursor("e\u{301}x".to_string(), 2);
+        assert_eq!(buffer.cursor(), 0);
+    }
+}
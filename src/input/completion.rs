@@ -0,0 +1,225 @@
+use super::Editor;
+use super::buffer::display_width;
+use crate::complete::{self, Completion, Quoting};
+use colored::Colorize;
+use crossterm::{
+    QueueableCommand, cursor,
+    event::{self, Event, KeyCode, KeyModifiers},
+    style, terminal,
+};
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthChar;
+
+/// 菜单中显示的名称：路径只显示最后一部分
+fn label(value: &str) -> &str {
+    match value.trim_end_matches('/').rfind('/') {
+        Some(i) => &value[i + 1..],
+        None => value,
+    }
+}
+
+/// 截断到 `width` 列以内
+fn truncate(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (i, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/// 候选项的引用形式，目录之外的候选项闭合引号
+fn quoted(value: &str, quoting: Quoting) -> String {
+    complete::quote(value, quoting, !value.ends_with('/'))
+}
+
+impl Editor {
+    /// 补全光标所在的单词：唯一的候选项补全后加上空格（目录加上 `/`），
+    /// 多个候选项时补全公共前缀，不能再补全时显示菜单
+    pub(super) async fn complete_word(&mut self) -> io::Result<Option<String>> {
+        let cursor = self.buffer.cursor();
+        let Completion {
+            start,
+            word,
+            quoting,
+            candidates,
+            no_space,
+            descriptions,
+        } = complete::complete(self.buffer.before_cursor()).await;
+        match candidates.as_slice() {
+            [] => {
+                print!("\x07");
+            }
+            [value] => {
+                let mut text = quoted(value, quoting);
+                if !no_space && !value.ends_with('/') {
+                    text.push(' ');
+                }
+                self.replace_range(start, cursor, &text);
+                self.refresh()?;
+            }
+            _ => {
+                let prefix = complete::common_prefix(&candidates);
+                if prefix.len() > word.len() {
+                    self.replace_range(start, cursor, &complete::quote(&prefix, quoting, false));
+                    self.refresh()?;
+                } else {
+                    self.completion_menu(start, quoting, &candidates, &descriptions)?;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn replace_range(&mut self, start: usize, end: usize, text: &str) {
+        self.buffer.remove_range(start, end);
+        self.buffer.insert(text);
+    }
+
+    /// 在当前行下方列出候选项：`Tab`/`Shift-Tab` 与上下键依次选中并插入候选项，
+    /// 回车确认，`Esc` 与 `Ctrl-G` 恢复原来的单词，其他按键关闭菜单后照常处理
+    fn completion_menu(
+        &mut self,
+        start: usize,
+        quoting: Quoting,
+        candidates: &[String],
+        descriptions: &[String],
+    ) -> io::Result<()> {
+        let original = self.buffer.before_cursor()[start..].to_string();
+        let count = candidates.len();
+        let mut selected: Option<usize> = None;
+        loop {
+            self.draw_menu(candidates, descriptions, selected)?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            let cancel = key.code == KeyCode::Esc || ctrl && key.code == KeyCode::Char('g');
+            let text = match key.code {
+                _ if cancel => {
+                    let cursor = self.buffer.cursor();
+                    self.replace_range(start, cursor, &original);
+                    return self.refresh();
+                }
+                KeyCode::Tab | KeyCode::Down => {
+                    let next = selected.map_or(0, |i| (i + 1) % count);
+                    selected = Some(next);
+                    quoted(&candidates[next], quoting)
+                }
+                KeyCode::BackTab | KeyCode::Up => {
+                    let next = selected.map_or(count - 1, |i| (i + count - 1) % count);
+                    selected = Some(next);
+                    quoted(&candidates[next], quoting)
+                }
+                KeyCode::Enter if selected.is_some() => return self.refresh(),
+                _ => {
+                    self.replay = Some(key);
+                    return self.refresh();
+                }
+            };
+            let cursor = self.buffer.cursor();
+            self.replace_range(start, cursor, &text);
+        }
+    }
+
+    /// 先重绘当前行，再在其下方按列输出候选项，之后光标回到原来的位置；
+    /// 有说明时每行一个候选项，说明显示在右侧
+    fn draw_menu(
+        &mut self,
+        candidates: &[String],
+        descriptions: &[String],
+        selected: Option<usize>,
+    ) -> io::Result<()> {
+        self.refresh()?;
+        let (columns, rows) = terminal::size().unwrap_or((80, 24));
+        let (columns, rows) = (columns.max(1) as usize, rows as usize);
+        let prompt_width = self.prompt_width as usize;
+        let end_row = (prompt_width + self.buffer.width()) / columns;
+        let down = end_row + 1 - self.cursor_row as usize;
+
+        let labels: Vec<&str> = candidates
+            .iter()
+            .map(|value| truncate(label(value), columns - 1))
+            .collect();
+        let cell = labels.iter().map(|l| display_width(l)).max().unwrap_or(0) + 2;
+        let described = descriptions.iter().any(|d| !d.is_empty());
+        let menu_columns = if described {
+            1
+        } else {
+            (columns / cell).max(1)
+        };
+        let menu_rows = candidates.len().div_ceil(menu_columns);
+        // 放不下时只显示选中项附近的几行，最后一行显示位置
+        let space = rows.saturating_sub(end_row + 3).max(2);
+        let (first, shown) = if menu_rows <= space {
+            (0, menu_rows)
+        } else {
+            let row = selected.map_or(0, |i| i % menu_rows);
+            let shown = space - 1;
+            (row.saturating_sub(shown - 1), shown)
+        };
+
+        let mut lines = Vec::new();
+        for row in first..first + shown {
+            let mut line = String::new();
+            for column in 0..menu_columns {
+                let index = column * menu_rows + row;
+                let Some(label) = labels.get(index) else {
+                    break;
+                };
+                let mut text = if candidates[index].ends_with('/') {
+                    label.blue().bold().to_string()
+                } else {
+                    label.to_string()
+                };
+                if selected == Some(index) {
+                    text = text.reversed().to_string();
+                }
+                line.push_str(&text);
+                if column + 1 < menu_columns && index + menu_rows < labels.len() {
+                    line.push_str(&" ".repeat(cell - display_width(label)));
+                }
+                if let Some(description) = descriptions.get(index)
+                    && !description.is_empty()
+                    && cell + 4 < columns
+                {
+                    let description = truncate(description, columns - cell - 4);
+                    line.push_str(&" ".repeat(cell - display_width(label)));
+                    line.push_str(&format!("-- {}", description).dimmed().to_string());
+                }
+            }
+            lines.push(line);
+        }
+        if shown < menu_rows {
+            lines.push(
+                format!("rows {}-{} of {}", first + 1, first + shown, menu_rows)
+                    .dimmed()
+                    .to_string(),
+            );
+        }
+
+        let mut stdout = io::stdout();
+        stdout.queue(style::Print("\r\n".repeat(down)))?;
+        stdout.queue(style::Print(lines.join("\r\n")))?;
+        stdout.queue(cursor::MoveUp((lines.len() - 1 + down) as u16))?;
+        let position = prompt_width + self.buffer.width_before_cursor();
+        stdout.queue(cursor::MoveToColumn((position % columns) as u16))?;
+        stdout.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu_label() {
+        assert_eq!(label("src/input/"), "input/");
+        assert_eq!(label("src/main.rs"), "main.rs");
+        assert_eq!(label("~/"), "~/");
+        assert_eq!(label("$HOME"), "$HOME");
+        assert_eq!(truncate("abcdef", 3), "abc");
+    }
+}
@@ -0,0 +1,527 @@
+use super::buffer::display_width;
+use super::{Editor, shell_quote};
+use crate::history::History;
+use colored::Colorize;
+use crossterm::{
+    QueueableCommand, cursor,
+    event::{self, Event, KeyCode, KeyModifiers},
+    style,
+    terminal::{self, ClearType},
+};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use unicode_width::UnicodeWidthChar;
+
+/// 每个匹配字符的得分
+const SCORE_MATCH: i64 = 16;
+/// 匹配之间每跳过一个字符的扣分，第一个跳过的字符扣分更多
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+/// 匹配在空白或 `/` 之后、在其他符号之后、在驼峰处的加分
+const BONUS_BOUNDARY_WHITE: i64 = 10;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+/// 连续匹配的加分至少为该值
+const BONUS_CONSECUTIVE: i64 = 4;
+
+/// 列出文件时最多访问的条目数，避免在很大的目录树中卡住
+const WALK_LIMIT: usize = 20000;
+
+/// 候选项的来源
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    History,
+    Files,
+    Directories,
+}
+
+fn bonus(previous: Option<char>, current: char) -> i64 {
+    match previous {
+        None => BONUS_BOUNDARY_WHITE,
+        Some(p) if p.is_whitespace() || p == '/' => BONUS_BOUNDARY_WHITE,
+        Some(p) if !p.is_alphanumeric() => BONUS_BOUNDARY,
+        Some(p) if p.is_lowercase() && current.is_uppercase() => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+/// 与 fzf 相同的模糊匹配：查询的字符须按顺序出现在候选项中，查询全为小写时忽略大小写。
+/// 先向前找到最早结束的匹配，再向后收缩到最短的范围，然后按匹配位置计分。
+/// 返回得分与匹配字符的下标（按字符计）
+pub fn score(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let ignore_case = !query.chars().any(char::is_uppercase);
+    let original: Vec<char> = candidate.chars().collect();
+    let text: Vec<char> = if ignore_case {
+        original
+            .iter()
+            .map(|c| c.to_lowercase().next().unwrap_or(*c))
+            .collect()
+    } else {
+        original.clone()
+    };
+    let query: Vec<char> = query.chars().collect();
+
+    let mut matched = 0;
+    let mut end = None;
+    for (i, c) in text.iter().enumerate() {
+        if *c == query[matched] {
+            matched += 1;
+            if matched == query.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+    let mut remaining = query.len();
+    let mut start = end;
+    for i in (0..=end).rev() {
+        if text[i] == query[remaining - 1] {
+            remaining -= 1;
+            if remaining == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    let mut score = 0;
+    let mut positions = Vec::with_capacity(query.len());
+    let mut next = 0;
+    let mut in_gap = false;
+    let mut consecutive_bonus = 0;
+    for i in start..=end {
+        if next < query.len() && text[i] == query[next] {
+            let previous = i.checked_sub(1).map(|p| original[p]);
+            let mut bonus = bonus(previous, original[i]);
+            if positions.last() == Some(&(i.wrapping_sub(1))) {
+                bonus = bonus.max(consecutive_bonus).max(BONUS_CONSECUTIVE);
+            }
+            consecutive_bonus = bonus;
+            // 第一个字符的位置更重要
+            score += SCORE_MATCH + if next == 0 { bonus * 2 } else { bonus };
+            positions.push(i);
+            next += 1;
+            in_gap = false;
+        } else {
+            score -= if in_gap {
+                PENALTY_GAP_EXTENSION
+            } else {
+                PENALTY_GAP_START
+            };
+            in_gap = true;
+            consecutive_bonus = 0;
+        }
+    }
+    Some((score, positions))
+}
+
+/// 按得分从高到低排列匹配的候选项，得分相同时较短的在前，查询为空时保持原来的顺序；
+/// 返回下标与匹配位置
+fn filter(items: &[String], query: &str) -> Vec<(usize, Vec<usize>)> {
+    let mut matches: Vec<(usize, i64, Vec<usize>)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| score(query, item).map(|(score, positions)| (i, score, positions)))
+        .collect();
+    matches.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then(if query.is_empty() {
+                Ordering::Equal
+            } else {
+                items[a.0].len().cmp(&items[b.0].len())
+            })
+            .then(a.0.cmp(&b.0))
+    });
+    matches
+        .into_iter()
+        .map(|(i, _, positions)| (i, positions))
+        .collect()
+}
+
+/// 截断到 `width` 列以内，`positions` 中的字符高亮显示
+fn render_line(text: &str, width: usize, positions: &[usize]) -> String {
+    let mut out = String::new();
+    let mut used = 0;
+    for (i, c) in text.chars().enumerate() {
+        let c = if c.is_control() { ' ' } else { c };
+        let w = c.width().unwrap_or(0);
+        if used + w > width {
+            break;
+        }
+        used += w;
+        if positions.contains(&i) {
+            out.push_str(&c.to_string().green().bold().to_string());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// 按宽度折行，用于预览窗格
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let mut current = String::new();
+        let mut used = 0;
+        for c in line.replace('\t', "    ").chars() {
+            let c = if c.is_control() { ' ' } else { c };
+            let w = c.width().unwrap_or(0);
+            if used + w > width {
+                lines.push(std::mem::take(&mut current));
+                used = 0;
+            }
+            current.push(c);
+            used += w;
+        }
+        lines.push(current);
+    }
+    lines
+}
+
+/// 预览窗格的内容：历史命令显示全文，目录显示其中的文件，文本文件显示开头部分
+fn preview(source: Source, item: &str, width: usize) -> Vec<String> {
+    if source == Source::History {
+        return wrap(item, width);
+    }
+    let path = Path::new(item);
+    if path.is_dir() {
+        let mut names: Vec<String> = fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| {
+                        let name = entry.file_name().to_string_lossy().into_owned();
+                        if entry.path().is_dir() {
+                            name + "/"
+                        } else {
+                            name
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        return names;
+    }
+    let mut bytes = Vec::new();
+    if let Ok(file) = fs::File::open(path) {
+        let _ = file.take(16 * 1024).read_to_end(&mut bytes);
+    }
+    if bytes.contains(&0) {
+        return vec!["(binary file)".to_string()];
+    }
+    wrap(&String::from_utf8_lossy(&bytes), width)
+}
+
+/// 当前目录下的文件或目录，按层次广度优先列出，跳过隐藏的文件与目录，不进入符号链接
+fn walk(directories_only: bool) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut queue = VecDeque::from([String::new()]);
+    let mut visited = 0;
+    while let Some(dir) = queue.pop_front() {
+        let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { &dir }) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            visited += 1;
+            if visited > WALK_LIMIT {
+                return found;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let path = format!("{}{}", dir, name);
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            if is_dir {
+                queue.push_back(format!("{}/", path));
+            }
+            if is_dir || !directories_only {
+                found.push(path);
+            }
+        }
+    }
+    found
+}
+
+/// 全屏的模糊查找界面
+struct Picker<'a> {
+    source: Source,
+    items: &'a [String],
+    multi: bool,
+    query: String,
+    matches: Vec<(usize, Vec<usize>)>,
+    /// 当前行在 `matches` 中的位置与列表滚动的位置
+    current: usize,
+    offset: usize,
+    selected: BTreeSet<usize>,
+}
+
+impl Picker<'_> {
+    /// 在备用屏幕中运行，返回选中的条目；取消时返回空
+    fn run(&mut self) -> io::Result<Vec<String>> {
+        let mut stdout = io::stdout();
+        stdout.queue(terminal::EnterAlternateScreen)?;
+        let result = self.event_loop();
+        stdout.queue(terminal::LeaveAlternateScreen)?;
+        stdout.flush()?;
+        result
+    }
+
+    fn event_loop(&mut self) -> io::Result<Vec<String>> {
+        self.matches = filter(self.items, &self.query);
+        loop {
+            self.render()?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            let count = self.matches.len();
+            match key.code {
+                KeyCode::Esc => return Ok(Vec::new()),
+                KeyCode::Char('c' | 'g') if ctrl => return Ok(Vec::new()),
+                KeyCode::Enter => {
+                    if self.selected.is_empty() {
+                        return Ok(self
+                            .matches
+                            .get(self.current)
+                            .map(|(i, _)| vec![self.items[*i].clone()])
+                            .unwrap_or_default());
+                    }
+                    return Ok(self
+                        .selected
+                        .iter()
+                        .map(|i| self.items[*i].clone())
+                        .collect());
+                }
+                KeyCode::Up => self.current = self.current.saturating_sub(1),
+                KeyCode::Char('p' | 'k') if ctrl => self.current = self.current.saturating_sub(1),
+                KeyCode::Down => self.current = (self.current + 1).min(count.saturating_sub(1)),
+                KeyCode::Char('n' | 'j') if ctrl => {
+                    self.current = (self.current + 1).min(count.saturating_sub(1))
+                }
+                KeyCode::PageUp => self.current = self.current.saturating_sub(self.page_size()),
+                KeyCode::PageDown => {
+                    self.current = (self.current + self.page_size()).min(count.saturating_sub(1))
+                }
+                KeyCode::Tab | KeyCode::BackTab if self.multi => {
+                    if let Some((i, _)) = self.matches.get(self.current)
+                        && !self.selected.remove(i)
+                    {
+                        self.selected.insert(*i);
+                    }
+                    self.current = if key.code == KeyCode::Tab {
+                        (self.current + 1).min(count.saturating_sub(1))
+                    } else {
+                        self.current.saturating_sub(1)
+                    };
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.update();
+                }
+                KeyCode::Char('u') if ctrl => {
+                    self.query.clear();
+                    self.update();
+                }
+                KeyCode::Char('w') if ctrl => {
+                    let trimmed = self.query.trim_end();
+                    let start = trimmed.rfind(' ').map_or(0, |i| i + 1);
+                    self.query.truncate(start);
+                    self.update();
+                }
+                KeyCode::Char(c) if !ctrl && !key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.query.push(c);
+                    self.update();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        self.matches = filter(self.items, &self.query);
+        self.current = 0;
+        self.offset = 0;
+    }
+
+    fn page_size(&self) -> usize {
+        let rows = terminal::size().map_or(24, |(_, rows)| rows) as usize;
+        rows.saturating_sub(2).max(1)
+    }
+
+    /// 第一行是查询，第二行是匹配数，其下是候选项；终端足够宽时右半边是预览窗格
+    fn render(&mut self) -> io::Result<()> {
+        let (columns, rows) = terminal::size().unwrap_or((80, 24));
+        let (columns, rows) = (columns as usize, rows as usize);
+        let list_width = if columns >= 60 { columns / 2 } else { columns };
+        let height = self.page_size();
+        if self.current < self.offset {
+            self.offset = self.current;
+        } else if self.current >= self.offset + height {
+            self.offset = self.current + 1 - height;
+        }
+
+        let mut stdout = io::stdout();
+        stdout.queue(terminal::Clear(ClearType::All))?;
+        stdout.queue(cursor::MoveTo(0, 1))?;
+        let mut info = format!("  {}/{}", self.matches.len(), self.items.len());
+        if !self.selected.is_empty() {
+            info.push_str(&format!(" ({} selected)", self.selected.len()));
+        }
+        stdout.queue(style::Print(render_line(&info, list_width, &[]).dimmed()))?;
+        for (row, (i, positions)) in self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(height)
+        {
+            let pointer = if row == self.current {
+                ">".red().bold().to_string()
+            } else {
+                " ".to_string()
+            };
+            let marker = if self.selected.contains(i) {
+                "+".magenta().to_string()
+            } else {
+                " ".to_string()
+            };
+            let line = render_line(&self.items[*i], list_width.saturating_sub(4), positions);
+            stdout.queue(cursor::MoveTo(0, (row - self.offset + 2) as u16))?;
+            stdout.queue(style::Print(format!("{}{} {}", pointer, marker, line)))?;
+        }
+
+        if list_width < columns {
+            let width = columns - list_width - 2;
+            let lines = self
+                .matches
+                .get(self.current)
+                .map(|(i, _)| preview(self.source, &self.items[*i], width))
+                .unwrap_or_default();
+            for row in 0..rows {
+                stdout.queue(cursor::MoveTo(list_width as u16, row as u16))?;
+                stdout.queue(style::Print("│".dimmed()))?;
+                if let Some(line) = lines.get(row) {
+                    stdout.queue(cursor::MoveTo((list_width + 2) as u16, row as u16))?;
+                    stdout.queue(style::Print(render_line(line, width, &[])))?;
+                }
+            }
+        }
+
+        let prompt = format!("> {}", self.query);
+        stdout.queue(cursor::MoveTo(0, 0))?;
+        stdout.queue(style::Print(render_line(&prompt, list_width, &[])))?;
+        let column = display_width(&prompt).min(list_width.saturating_sub(1));
+        stdout.queue(cursor::MoveTo(column as u16, 0))?;
+        stdout.flush()
+    }
+}
+
+/// 供 `zi` 这样的命令使用：在模糊查找界面中选择一个目录，取消时返回 `None`。
+/// 此时行编辑器不在运行，需要自行进入与退出原始模式
+pub fn pick_directory(items: &[String]) -> io::Result<Option<String>> {
+    let mut picker = Picker {
+        source: Source::Directories,
+        items,
+        multi: false,
+        query: String::new(),
+        matches: Vec::new(),
+        current: 0,
+        offset: 0,
+        selected: BTreeSet::new(),
+    };
+    terminal::enable_raw_mode()?;
+    let chosen = picker.run();
+    terminal::disable_raw_mode()?;
+    Ok(chosen?.into_iter().next())
+}
+
+impl Editor {
+    /// 在全屏的模糊查找界面中选择历史命令、文件或目录：
+    /// 历史命令替换当前行，文件与目录（可以用 Tab 多选）插入到光标处
+    pub(super) async fn fuzzy_find(&mut self, source: Source) -> io::Result<Option<String>> {
+        let items: Vec<String> = match source {
+            Source::History => {
+                let mut seen = HashSet::new();
+                History::all()
+                    .await
+                    .into_iter()
+                    .rev()
+                    .filter(|command| seen.insert(command.clone()))
+                    .collect()
+            }
+            Source::Files => walk(false),
+            Source::Directories => walk(true),
+        };
+        let mut picker = Picker {
+            source,
+            items: &items,
+            multi: source != Source::History,
+            query: String::new(),
+            matches: Vec::new(),
+            current: 0,
+            offset: 0,
+            selected: BTreeSet::new(),
+        };
+        let chosen = picker.run()?;
+        if !chosen.is_empty() {
+            if source == Source::History {
+                self.buffer.set(&chosen[0]);
+            } else {
+                for path in chosen {
+                    self.buffer.insert(&format!("{} ", shell_quote(&path)));
+                }
+            }
+        }
+        self.refresh()?;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(score("", "anything"), Some((0, Vec::new())));
+        assert_eq!(
+            score("gco", "git checkout").map(|m| m.1),
+            Some(vec![0, 4, 9])
+        );
+        assert_eq!(score("xyz", "git checkout"), None);
+        // 大写的查询区分大小写
+        assert!(score("Git", "git push").is_none());
+        assert!(score("git", "Git push").is_some());
+        // 连续匹配与单词开头的匹配得分更高
+        let (consecutive, _) = score("push", "git push").unwrap();
+        let (scattered, _) = score("push", "pull --rush").unwrap();
+        assert!(consecutive > scattered);
+        let (boundary, _) = score("b", "src/build.rs").unwrap();
+        let (inner, _) = score("b", "src/lib.rs").unwrap();
+        assert!(boundary > inner);
+    }
+
+    #[test]
+    fn test_fuzzy_filter_order() {
+        let items: Vec<String> = ["cargo build --release", "cd build", "abuild"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let order: Vec<usize> = filter(&items, "build").into_iter().map(|m| m.0).collect();
+        assert_eq!(order, vec![1, 0, 2]);
+        let order: Vec<usize> = filter(&items, "").into_iter().map(|m| m.0).collect();
+        assert_eq!(order, vec![0, 1, 2]);
+    }
+}
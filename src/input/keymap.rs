@@ -0,0 +1,519 @@
+use crate::options;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// 行编辑器的操作，名称与 readline 的同名命令一致
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    AcceptLine,
+    Interrupt,
+    /// 空行时结束输入，否则删除光标处的字符
+    DeleteCharOrEof,
+    SelfInsert(char),
+    BackwardChar,
+    ForwardChar,
+    BeginningOfLine,
+    EndOfLine,
+    BackwardWord,
+    ForwardWord,
+    BackwardDeleteChar,
+    DeleteChar,
+    KillLine,
+    UnixLineDiscard,
+    UnixWordRubout,
+    KillWord,
+    BackwardKillWord,
+    Yank,
+    YankPop,
+    TransposeChars,
+    ClearScreen,
+    YankLastArg,
+    PreviousHistory,
+    NextHistory,
+    /// 从 vi 插入模式回到普通模式
+    ViMovementMode,
+    /// 在 `$VISUAL` 或 `$EDITOR` 中编辑当前行，保存后执行
+    EditAndExecuteCommand,
+    /// `Ctrl-R` 与 `Ctrl-S` 的增量搜索
+    ReverseSearchHistory,
+    ForwardSearchHistory,
+    /// `Tab` 补全光标所在的单词
+    Complete,
+    /// 全屏的模糊查找：历史命令、当前目录下的文件与目录
+    FuzzyHistory,
+    FuzzyFile,
+    FuzzyDirectory,
+}
+
+/// 可以绑定到按键的操作及其名称；`SelfInsert` 是未绑定的普通字符的默认操作
+pub const ACTIONS: &[(&str, Action)] = &[
+    ("accept-line", Action::AcceptLine),
+    ("backward-char", Action::BackwardChar),
+    ("backward-delete-char", Action::BackwardDeleteChar),
+    ("backward-kill-word", Action::BackwardKillWord),
+    ("backward-word", Action::BackwardWord),
+    ("beginning-of-line", Action::BeginningOfLine),
+    ("clear-screen", Action::ClearScreen),
+    ("complete", Action::Complete),
+    ("delete-char", Action::DeleteChar),
+    ("delete-char-or-eof", Action::DeleteCharOrEof),
+    ("edit-and-execute-command", Action::EditAndExecuteCommand),
+    ("end-of-line", Action::EndOfLine),
+    ("forward-char", Action::ForwardChar),
+    ("forward-search-history", Action::ForwardSearchHistory),
+    ("forward-word", Action::ForwardWord),
+    ("fuzzy-directory", Action::FuzzyDirectory),
+    ("fuzzy-file", Action::FuzzyFile),
+    ("fuzzy-history", Action::FuzzyHistory),
+    ("interrupt", Action::Interrupt),
+    ("kill-line", Action::KillLine),
+    ("kill-word", Action::KillWord),
+    ("next-history", Action::NextHistory),
+    ("previous-history", Action::PreviousHistory),
+    ("reverse-search-history", Action::ReverseSearchHistory),
+    ("transpose-chars", Action::TransposeChars),
+    ("unix-line-discard", Action::UnixLineDiscard),
+    ("unix-word-rubout", Action::UnixWordRubout),
+    ("vi-movement-mode", Action::ViMovementMode),
+    ("yank", Action::Yank),
+    ("yank-last-arg", Action::YankLastArg),
+    ("yank-pop", Action::YankPop),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, action)| *action)
+    }
+}
+
+/// 行编辑器当前的键位
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Emacs,
+    ViInsert,
+    /// vi 普通模式的按键由 `vi` 模块解析
+    ViNormal,
+}
+
+/// 按键绑定表，与 readline 的 keymap 同名
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Keymap {
+    Emacs,
+    ViInsert,
+    ViCommand,
+}
+
+impl Keymap {
+    pub fn from_name(name: &str) -> Option<Keymap> {
+        match name {
+            "emacs" | "emacs-standard" => Some(Keymap::Emacs),
+            "vi-insert" => Some(Keymap::ViInsert),
+            "vi" | "vi-command" | "vi-move" => Some(Keymap::ViCommand),
+            _ => None,
+        }
+    }
+
+    /// 当前编辑模式下 `bind` 默认修改的绑定表
+    pub fn current() -> Keymap {
+        if options::is_set("vi") {
+            Keymap::ViInsert
+        } else {
+            Keymap::Emacs
+        }
+    }
+
+    pub fn of(mode: Mode) -> Keymap {
+        match mode {
+            Mode::Emacs => Keymap::Emacs,
+            Mode::ViInsert => Keymap::ViInsert,
+            Mode::ViNormal => Keymap::ViCommand,
+        }
+    }
+}
+
+/// 按键序列中的一个按键，忽略 crossterm 报告的按键状态与字符自带的 Shift
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key { code, modifiers }
+    }
+}
+
+impl From<&KeyEvent> for Key {
+    fn from(event: &KeyEvent) -> Key {
+        let mut modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let code = match event.code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            KeyCode::BackTab => {
+                modifiers |= KeyModifiers::SHIFT;
+                KeyCode::Tab
+            }
+            code => code,
+        };
+        Key::new(code, modifiers)
+    }
+}
+
+/// 按键绑定的内容：编辑操作或 `bind -x` 的 shell 命令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding {
+    Action(Action),
+    Command(String),
+}
+
+/// 没有可打印形式、以终端转义序列表示的按键
+const SPECIAL_KEYS: &[(&str, KeyCode, KeyModifiers)] = &[
+    ("\\e[1;5A", KeyCode::Up, KeyModifiers::CONTROL),
+    ("\\e[1;5B", KeyCode::Down, KeyModifiers::CONTROL),
+    ("\\e[1;5C", KeyCode::Right, KeyModifiers::CONTROL),
+    ("\\e[1;5D", KeyCode::Left, KeyModifiers::CONTROL),
+    ("\\e[1;3A", KeyCode::Up, KeyModifiers::ALT),
+    ("\\e[1;3B", KeyCode::Down, KeyModifiers::ALT),
+    ("\\e[1;3C", KeyCode::Right, KeyModifiers::ALT),
+    ("\\e[1;3D", KeyCode::Left, KeyModifiers::ALT),
+    ("\\e[3~", KeyCode::Delete, KeyModifiers::NONE),
+    ("\\e[A", KeyCode::Up, KeyModifiers::NONE),
+    ("\\e[B", KeyCode::Down, KeyModifiers::NONE),
+    ("\\e[C", KeyCode::Right, KeyModifiers::NONE),
+    ("\\e[D", KeyCode::Left, KeyModifiers::NONE),
+    ("\\e[H", KeyCode::Home, KeyModifiers::NONE),
+    ("\\e[F", KeyCode::End, KeyModifiers::NONE),
+    ("\\e[Z", KeyCode::Tab, KeyModifiers::SHIFT),
+    ("\\e\\C-?", KeyCode::Backspace, KeyModifiers::ALT),
+    ("\\C-?", KeyCode::Backspace, KeyModifiers::NONE),
+    ("\\C-m", KeyCode::Enter, KeyModifiers::NONE),
+    ("\\C-i", KeyCode::Tab, KeyModifiers::NONE),
+    ("\\r", KeyCode::Enter, KeyModifiers::NONE),
+    ("\\t", KeyCode::Tab, KeyModifiers::NONE),
+    ("\\e", KeyCode::Esc, KeyModifiers::NONE),
+    ("\\C-[", KeyCode::Esc, KeyModifiers::NONE),
+];
+
+/// 解析 readline 风格的按键序列，例如 `\C-x\C-e`、`\ef`、`\M-.`、`\e[A`
+pub fn parse_keyseq(keyseq: &str) -> Result<Vec<Key>, String> {
+    let mut keys = Vec::new();
+    let mut rest = keyseq;
+    while !rest.is_empty() {
+        let alt = if let Some(after) = rest.strip_prefix("\\M-") {
+            rest = after;
+            true
+        } else {
+            false
+        };
+        let (mut key, after) = parse_key(rest)?;
+        rest = after;
+        // `\e` 之后紧跟的按键与终端报告的 Alt 组合键相同
+        if key.code == KeyCode::Esc && !rest.is_empty() {
+            let (next, after) = parse_key(rest)?;
+            key = Key::new(next.code, next.modifiers | KeyModifiers::ALT);
+            rest = after;
+        }
+        if alt {
+            key.modifiers |= KeyModifiers::ALT;
+        }
+        keys.push(key);
+    }
+    if keys.is_empty() {
+        return Err("empty key sequence".to_string());
+    }
+    Ok(keys)
+}
+
+/// 解析一个按键，返回按键与剩余的文本
+fn parse_key(keyseq: &str) -> Result<(Key, &str), String> {
+    if let Some((seq, code, modifiers)) = SPECIAL_KEYS
+        .iter()
+        .find(|(seq, _, _)| keyseq.starts_with(seq))
+    {
+        return Ok((Key::new(*code, *modifiers), &keyseq[seq.len()..]));
+    }
+    if let Some(after) = keyseq.strip_prefix("\\C-") {
+        let mut chars = after.chars();
+        let c = chars
+            .next()
+            .ok_or_else(|| format!("{}: incomplete control key", keyseq))?;
+        let key = Key::new(KeyCode::Char(c.to_ascii_lowercase()), KeyModifiers::CONTROL);
+        return Ok((key, chars.as_str()));
+    }
+    let mut chars = keyseq.chars();
+    let c = match chars.next() {
+        Some('\\') => chars.next().unwrap_or('\\'),
+        Some(c) => c,
+        None => return Err("empty key sequence".to_string()),
+    };
+    Ok((
+        Key::new(KeyCode::Char(c), KeyModifiers::NONE),
+        chars.as_str(),
+    ))
+}
+
+/// 按键序列的 readline 表示，可以重新被 `parse_keyseq` 读入
+pub fn keyseq_to_string(keys: &[Key]) -> String {
+    let mut out = String::new();
+    for key in keys {
+        if let Some((seq, _, _)) = SPECIAL_KEYS
+            .iter()
+            .find(|(_, code, modifiers)| *code == key.code && *modifiers == key.modifiers)
+        {
+            out.push_str(seq);
+            continue;
+        }
+        let plain = key.modifiers - KeyModifiers::ALT;
+        if key.modifiers.contains(KeyModifiers::ALT) {
+            out.push_str("\\e");
+        }
+        match key.code {
+            KeyCode::Char(c) => {
+                if plain.contains(KeyModifiers::CONTROL) {
+                    out.push_str("\\C-");
+                }
+                if matches!(c, '\\' | '"') {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            code => match SPECIAL_KEYS
+                .iter()
+                .find(|(_, special, modifiers)| *special == code && *modifiers == plain)
+            {
+                Some((seq, _, _)) => out.push_str(seq),
+                None => out.push_str(&format!("{:?}", code)),
+            },
+        }
+    }
+    out
+}
+
+/// Emacs 风格的默认按键
+const EMACS: &[(&str, &str)] = &[
+    ("\\C-m", "accept-line"),
+    ("\\C-j", "accept-line"),
+    ("\\C-c", "interrupt"),
+    ("\\C-d", "delete-char-or-eof"),
+    ("\\C-a", "beginning-of-line"),
+    ("\\C-b", "backward-char"),
+    ("\\C-e", "end-of-line"),
+    ("\\C-f", "forward-char"),
+    ("\\C-h", "backward-delete-char"),
+    ("\\C-?", "backward-delete-char"),
+    ("\\C-i", "complete"),
+    ("\\e[3~", "delete-char"),
+    ("\\C-k", "kill-line"),
+    ("\\C-r", "reverse-search-history"),
+    ("\\C-s", "forward-search-history"),
+    ("\\C-l", "clear-screen"),
+    ("\\C-n", "next-history"),
+    ("\\C-p", "previous-history"),
+    ("\\C-t", "transpose-chars"),
+    ("\\C-u", "unix-line-discard"),
+    ("\\C-w", "unix-word-rubout"),
+    ("\\C-y", "yank"),
+    ("\\C-x\\C-e", "edit-and-execute-command"),
+    ("\\C-x\\C-f", "fuzzy-file"),
+    ("\\er", "fuzzy-history"),
+    ("\\ec", "fuzzy-directory"),
+    ("\\eb", "backward-word"),
+    ("\\ef", "forward-word"),
+    ("\\ed", "kill-word"),
+    ("\\e\\C-?", "backward-kill-word"),
+    ("\\ey", "yank-pop"),
+    ("\\e.", "yank-last-arg"),
+    ("\\e_", "yank-last-arg"),
+    ("\\e[A", "previous-history"),
+    ("\\e[B", "next-history"),
+    ("\\e[C", "forward-char"),
+    ("\\e[D", "backward-char"),
+    ("\\e[1;5C", "forward-word"),
+    ("\\e[1;5D", "backward-word"),
+    ("\\e[1;3C", "forward-word"),
+    ("\\e[1;3D", "backward-word"),
+    ("\\e[H", "beginning-of-line"),
+    ("\\e[F", "end-of-line"),
+];
+
+/// vi 插入模式：`Esc` 回到普通模式，只保留 readline 在 vi 插入模式下也有的控制键
+const VI_INSERT: &[(&str, &str)] = &[
+    ("\\e", "vi-movement-mode"),
+    ("\\C-m", "accept-line"),
+    ("\\C-j", "accept-line"),
+    ("\\C-c", "interrupt"),
+    ("\\C-d", "delete-char-or-eof"),
+    ("\\C-h", "backward-delete-char"),
+    ("\\C-?", "backward-delete-char"),
+    ("\\C-i", "complete"),
+    ("\\e[3~", "delete-char"),
+    ("\\C-r", "reverse-search-history"),
+    ("\\C-s", "forward-search-history"),
+    ("\\C-l", "clear-screen"),
+    ("\\C-t", "transpose-chars"),
+    ("\\C-u", "unix-line-discard"),
+    ("\\C-w", "unix-word-rubout"),
+    ("\\C-y", "yank"),
+    ("\\e[A", "previous-history"),
+    ("\\e[B", "next-history"),
+    ("\\e[C", "forward-char"),
+    ("\\e[D", "backward-char"),
+    ("\\e[H", "beginning-of-line"),
+    ("\\e[F", "end-of-line"),
+];
+
+/// vi 普通模式中的字符命令由 `vi` 模块解析，这里只有不可打印的按键
+const VI_COMMAND: &[(&str, &str)] = &[
+    ("\\C-m", "accept-line"),
+    ("\\C-j", "accept-line"),
+    ("\\C-c", "interrupt"),
+    ("\\C-d", "delete-char-or-eof"),
+    ("\\C-?", "backward-char"),
+    ("\\e[3~", "delete-char"),
+    ("\\C-r", "reverse-search-history"),
+    ("\\C-s", "forward-search-history"),
+    ("\\C-l", "clear-screen"),
+    ("\\e[A", "previous-history"),
+    ("\\e[B", "next-history"),
+    ("\\e[C", "forward-char"),
+    ("\\e[D", "backward-char"),
+    ("\\e[H", "beginning-of-line"),
+    ("\\e[F", "end-of-line"),
+];
+
+type Table = Vec<(Vec<Key>, Binding)>;
+
+fn defaults(bindings: &[(&str, &str)]) -> Table {
+    bindings
+        .iter()
+        .map(|(keyseq, name)| {
+            let keys = parse_keyseq(keyseq).expect("default key sequence");
+            let action = Action::from_name(name).expect("default action");
+            (keys, Binding::Action(action))
+        })
+        .collect()
+}
+
+lazy_static! {
+    static ref BINDINGS: RwLock<HashMap<Keymap, Table>> = RwLock::new(HashMap::from([
+        (Keymap::Emacs, defaults(EMACS)),
+        (Keymap::ViInsert, defaults(VI_INSERT)),
+        (Keymap::ViCommand, defaults(VI_COMMAND)),
+    ]));
+}
+
+/// 查找按键序列的结果
+pub enum Lookup {
+    Bound(Binding),
+    /// 按键序列是更长的绑定的前缀，等待后续按键
+    Prefix,
+    Unbound,
+}
+
+pub fn lookup(keymap: Keymap, keys: &[Key]) -> Lookup {
+    let bindings = BINDINGS.read().unwrap();
+    let table = &bindings[&keymap];
+    if table
+        .iter()
+        .any(|(bound, _)| bound.len() > keys.len() && bound.starts_with(keys))
+    {
+        return Lookup::Prefix;
+    }
+    match table.iter().find(|(bound, _)| bound == keys) {
+        Some((_, binding)) => Lookup::Bound(binding.clone()),
+        None => Lookup::Unbound,
+    }
+}
+
+/// 绑定按键序列，替换已有的绑定
+pub fn bind(keymap: Keymap, keys: Vec<Key>, binding: Binding) {
+    let mut bindings = BINDINGS.write().unwrap();
+    let table = bindings.get_mut(&keymap).unwrap();
+    match table.iter_mut().find(|(bound, _)| *bound == keys) {
+        Some(entry) => entry.1 = binding,
+        None => table.push((keys, binding)),
+    }
+}
+
+/// 删除按键序列的绑定，返回它之前是否存在
+pub fn unbind(keymap: Keymap, keys: &[Key]) -> bool {
+    let mut bindings = BINDINGS.write().unwrap();
+    let table = bindings.get_mut(&keymap).unwrap();
+    let before = table.len();
+    table.retain(|(bound, _)| bound != keys);
+    table.len() != before
+}
+
+/// 删除绑定到 `action` 的全部按键
+pub fn unbind_action(keymap: Keymap, action: Action) {
+    let mut bindings = BINDINGS.write().unwrap();
+    bindings
+        .get_mut(&keymap)
+        .unwrap()
+        .retain(|(_, binding)| *binding != Binding::Action(action));
+}
+
+pub fn bindings(keymap: Keymap) -> Vec<(Vec<Key>, Binding)> {
+    BINDINGS.read().unwrap()[&keymap].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keyseq() {
+        let ctrl = |c| Key::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        let alt = |c| Key::new(KeyCode::Char(c), KeyModifiers::ALT);
+        assert_eq!(parse_keyseq("\\C-x\\C-E"), Ok(vec![ctrl('x'), ctrl('e')]));
+        assert_eq!(parse_keyseq("\\ef"), Ok(vec![alt('f')]));
+        assert_eq!(parse_keyseq("\\M-."), Ok(vec![alt('.')]));
+        assert_eq!(
+            parse_keyseq("\\e[A"),
+            Ok(vec![Key::new(KeyCode::Up, KeyModifiers::NONE)])
+        );
+        assert_eq!(
+            parse_keyseq("\\e"),
+            Ok(vec![Key::new(KeyCode::Esc, KeyModifiers::NONE)])
+        );
+        assert!(parse_keyseq("").is_err());
+        for (keyseq, _) in EMACS.iter().chain(VI_INSERT) {
+            let keys = parse_keyseq(keyseq).unwrap();
+            assert_eq!(keyseq_to_string(&keys), *keyseq);
+        }
+        assert_eq!(
+            keyseq_to_string(&parse_keyseq("g\\\"\\e\\\\").unwrap()),
+            "g\\\"\\e\\\\"
+        );
+    }
+
+    #[test]
+    fn test_lookup_sequences() {
+        let keys = parse_keyseq("\\C-xg").unwrap();
+        bind(
+            Keymap::ViCommand,
+            keys.clone(),
+            Binding::Command("true".into()),
+        );
+        assert!(matches!(
+            lookup(Keymap::ViCommand, &keys[..1]),
+            Lookup::Prefix
+        ));
+        assert!(matches!(
+            lookup(Keymap::ViCommand, &keys),
+            Lookup::Bound(Binding::Command(_))
+        ));
+        assert!(unbind(Keymap::ViCommand, &keys));
+        assert!(matches!(
+            lookup(Keymap::ViCommand, &keys[..1]),
+            Lookup::Unbound
+        ));
+    }
+}
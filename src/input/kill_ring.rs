@@ -0,0 +1,71 @@
+use lazy_static::lazy_static;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// 最多保存的删除文本条数
+const CAPACITY: usize = 32;
+
+/// 删除的文本，在多次读取命令之间保留，`Ctrl-Y` 取回最近一条，`Alt-Y` 依次轮换
+#[derive(Default)]
+struct KillRing {
+    entries: VecDeque<String>,
+    /// 最近一次 yank 取出的条目
+    yank_index: usize,
+}
+
+lazy_static! {
+    static ref KILL_RING: Mutex<KillRing> = Mutex::new(KillRing::default());
+}
+
+/// 连续删除时合并为一条：向后删除的文本接在末尾，向前删除的文本接在开头
+pub fn kill(text: &str, backward: bool, append: bool) {
+    if text.is_empty() {
+        return;
+    }
+    let mut ring = KILL_RING.lock().unwrap();
+    match ring.entries.front_mut() {
+        Some(last) if append => {
+            if backward {
+                last.insert_str(0, text);
+            } else {
+                last.push_str(text);
+            }
+        }
+        _ => {
+            ring.entries.push_front(text.to_string());
+            ring.entries.truncate(CAPACITY);
+        }
+    }
+}
+
+pub fn yank() -> Option<String> {
+    let mut ring = KILL_RING.lock().unwrap();
+    ring.yank_index = 0;
+    ring.entries.front().cloned()
+}
+
+/// 紧接着 yank 时取出更早的一条
+pub fn yank_pop() -> Option<String> {
+    let mut ring = KILL_RING.lock().unwrap();
+    if ring.entries.is_empty() {
+        return None;
+    }
+    ring.yank_index = (ring.yank_index + 1) % ring.entries.len();
+    ring.entries.get(ring.yank_index).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kill_ring() {
+        kill("world", false, false);
+        kill("!", false, true);
+        kill("hello ", true, true);
+        assert_eq!(yank().as_deref(), Some("hello world!"));
+        kill("second", false, false);
+        assert_eq!(yank().as_deref(), Some("second"));
+        assert_eq!(yank_pop().as_deref(), Some("hello world!"));
+    }
+}
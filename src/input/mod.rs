@@ -0,0 +1,685 @@
+mod buffer;
+mod completion;
+mod fuzzy;
+pub(crate) mod keymap;
+mod kill_ring;
+mod search;
+mod vi;
+
+use crate::{abbr, alias, exec, history, options, println_error, var};
+use buffer::{LineBuffer, visible};
+use crossterm::{
+    QueueableCommand, cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    style,
+    terminal::{self, ClearType},
+};
+pub(crate) use fuzzy::pick_directory;
+use keymap::{Action, Binding, Key, Keymap, Lookup, Mode};
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, IsTerminal, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+/// 标准输入是否为终端；否则不使用行编辑器，按普通文本逐行读取
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// 打印提示符并读取一条命令；标准输入不是终端时不打印提示符
+pub async fn read_command(prompt: &str, prompt_width: u16) -> io::Result<String> {
+    if !is_interactive() {
+        return read_plain_command();
+    }
+
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    terminal::enable_raw_mode()?;
+    let mut editor = Editor::new(prompt, prompt_width);
+    editor.show_mode()?;
+    let result = editor.run().await;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+/// 上一个操作，决定删除是否合并到同一条、`Alt-Y` 与 `Alt-.` 能否继续轮换
+#[derive(Clone, Copy, PartialEq)]
+enum LastAction {
+    Other,
+    Kill,
+    /// 刚插入的文本范围
+    Yank {
+        start: usize,
+        end: usize,
+    },
+    /// 刚插入的参数范围及其来自第几条历史命令
+    LastArg {
+        start: usize,
+        end: usize,
+        index: usize,
+    },
+}
+
+/// 交互式行编辑器：编辑 `LineBuffer`，每次修改后按显示宽度重绘当前行
+struct Editor {
+    buffer: LineBuffer,
+    prompt: String,
+    prompt_width: u16,
+    /// 光标相对提示符所在行向下的行数，行内容超过终端宽度时会折行
+    cursor_row: u16,
+    /// 当前显示的是倒数第几条历史命令，0 表示正在输入的行
+    history_index: usize,
+    /// 离开正在输入的行时保存的内容，回到最底部时恢复
+    saved_line: String,
+    /// 上下键只在以此开头的历史命令中移动
+    history_prefix: String,
+    last_action: LastAction,
+    mode: Mode,
+    /// 尚未构成完整绑定的按键序列
+    keys: Vec<Key>,
+    /// 结束增量搜索的按键，需要在搜索之后再处理一次
+    replay: Option<KeyEvent>,
+    vi: vi::ViState,
+}
+
+impl Editor {
+    fn new(prompt: &str, prompt_width: u16) -> Self {
+        Editor {
+            buffer: LineBuffer::new(),
+            prompt: prompt.to_string(),
+            prompt_width,
+            cursor_row: 0,
+            history_index: 0,
+            saved_line: String::new(),
+            history_prefix: String::new(),
+            last_action: LastAction::Other,
+            mode: if options::is_set("vi") {
+                Mode::ViInsert
+            } else {
+                Mode::Emacs
+            },
+            keys: Vec::new(),
+            replay: None,
+            vi: vi::ViState::default(),
+        }
+    }
+
+    async fn run(&mut self) -> io::Result<String> {
+        loop {
+            io::stdout().flush()?;
+            let key = match self.replay.take() {
+                Some(key) => key,
+                None => match event::read()? {
+                    Event::Key(key) => key,
+                    _ => continue,
+                },
+            };
+            if let Some(line) = self.handle_key(key).await? {
+                return Ok(line);
+            }
+        }
+    }
+
+    /// 按当前键位的绑定表处理一个按键，接受输入时返回命令
+    async fn handle_key(&mut self, key: KeyEvent) -> io::Result<Option<String>> {
+        self.keys.push(Key::from(&key));
+        match keymap::lookup(Keymap::of(self.mode), &self.keys) {
+            Lookup::Prefix => return Ok(None),
+            Lookup::Bound(binding) => {
+                self.keys.clear();
+                let line = match binding {
+                    Binding::Action(action) => self.perform(action).await?,
+                    Binding::Command(command) => self.run_bound_command(&command).await?,
+                };
+                if line.is_none() && self.clamp_cursor() {
+                    self.refresh()?;
+                }
+                return Ok(line);
+            }
+            // 未绑定的按键序列被丢弃，单个按键按当前模式的默认方式处理
+            Lookup::Unbound if self.keys.len() > 1 => {
+                self.keys.clear();
+                return Ok(None);
+            }
+            Lookup::Unbound => self.keys.clear(),
+        }
+        let plain = !key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        match (self.mode, key.code) {
+            (Mode::ViNormal, _) => self.vi_command(key).await,
+            // 终端把紧跟在 `Esc` 之后的按键报告为 Alt 组合键
+            (Mode::ViInsert, KeyCode::Char(c)) if key.modifiers == KeyModifiers::ALT => {
+                self.perform(Action::ViMovementMode).await?;
+                self.vi_command(KeyEvent::from(KeyCode::Char(c))).await
+            }
+            (_, KeyCode::Char(c)) if plain => self.perform(Action::SelfInsert(c)).await,
+            _ => Ok(None),
+        }
+    }
+
+    /// `bind -x` 绑定的命令：通过 `READLINE_LINE` 与 `READLINE_POINT` 读取并修改当前行，
+    /// 命令的输出显示在提示符的位置，之后重新显示提示符与当前行
+    async fn run_bound_command(&mut self, command: &str) -> io::Result<Option<String>> {
+        let point = self.buffer.before_cursor().chars().count();
+        var::set_scalar("READLINE_LINE", self.buffer.text(), false);
+        var::set_scalar("READLINE_POINT", &point.to_string(), false);
+        let mut stdout = io::stdout();
+        if self.cursor_row > 0 {
+            stdout.queue(cursor::MoveUp(self.cursor_row))?;
+        }
+        stdout.queue(cursor::MoveToColumn(0))?;
+        stdout.queue(terminal::Clear(ClearType::FromCursorDown))?;
+        stdout.flush()?;
+        terminal::disable_raw_mode()?;
+        if let Err(e) = exec::execute_line(command).await {
+            println_error!("{}", e);
+        }
+        terminal::enable_raw_mode()?;
+
+        let line = var::get("READLINE_LINE").unwrap_or_default();
+        let point = var::get("READLINE_POINT")
+            .and_then(|point| point.parse::<usize>().ok())
+            .map_or(line.len(), |point| {
+                line.char_indices()
+                    .nth(point)
+                    .map_or(line.len(), |(i, _)| i)
+            });
+        var::unset("READLINE_LINE");
+        var::unset("READLINE_POINT");
+        self.buffer.set_with_cursor(line, point);
+        print!("{}", self.prompt);
+        self.cursor_row = 0;
+        self.refresh()?;
+        Ok(None)
+    }
+
+    /// 执行一个编辑操作，接受输入时返回命令
+    async fn perform(&mut self, action: Action) -> io::Result<Option<String>> {
+        let last_action = std::mem::replace(&mut self.last_action, LastAction::Other);
+        let changed = match action {
+            Action::Interrupt => {
+                self.finish("^C")?;
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "Interrupted"));
+            }
+            Action::DeleteCharOrEof if self.buffer.is_empty() => {
+                self.finish("^D")?;
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "EOF"));
+            }
+            Action::AcceptLine => {
+                self.expand_abbreviation();
+                self.finish("")?;
+                return Ok(Some(self.buffer.text().to_string()));
+            }
+            Action::SelfInsert(c) => {
+                if c == ' ' {
+                    self.expand_abbreviation();
+                }
+                self.buffer.insert(c.encode_utf8(&mut [0; 4]));
+                self.vi.record_insert(Some(c));
+                true
+            }
+            Action::BackwardChar => self.buffer.move_left(),
+            Action::ForwardChar => self.buffer.move_right(),
+            Action::BeginningOfLine => {
+                self.buffer.move_home();
+                true
+            }
+            Action::EndOfLine => {
+                self.buffer.move_end();
+                true
+            }
+            Action::BackwardWord => {
+                let start = self.buffer.word_start_before(buffer::is_alphanumeric);
+                self.buffer.set_cursor(start);
+                true
+            }
+            Action::ForwardWord => {
+                let end = self.buffer.word_end_after(buffer::is_alphanumeric);
+                self.buffer.set_cursor(end);
+                true
+            }
+            Action::BackwardDeleteChar => {
+                self.vi.record_insert(None);
+                self.buffer.backspace()
+            }
+            Action::DeleteChar | Action::DeleteCharOrEof => self.buffer.delete(),
+            Action::KillLine => {
+                let end = self.buffer.text().len();
+                self.kill(self.buffer.cursor(), end, last_action)
+            }
+            Action::UnixLineDiscard => self.kill(0, self.buffer.cursor(), last_action),
+            Action::UnixWordRubout => {
+                let start = self.buffer.word_start_before(buffer::is_not_whitespace);
+                self.kill(start, self.buffer.cursor(), last_action)
+            }
+            Action::BackwardKillWord => {
+                let start = self.buffer.word_start_before(buffer::is_alphanumeric);
+                self.kill(start, self.buffer.cursor(), last_action)
+            }
+            Action::KillWord => {
+                let end = self.buffer.word_end_after(buffer::is_alphanumeric);
+                self.kill(self.buffer.cursor(), end, last_action)
+            }
+            Action::Yank => match kill_ring::yank() {
+                Some(text) => {
+                    self.insert_recorded(&text, |start, end| LastAction::Yank { start, end });
+                    true
+                }
+                None => false,
+            },
+            Action::YankPop => match (last_action, kill_ring::yank_pop()) {
+                (LastAction::Yank { start, end }, Some(text)) => {
+                    self.buffer.remove_range(start, end);
+                    self.insert_recorded(&text, |start, end| LastAction::Yank { start, end });
+                    true
+                }
+                _ => false,
+            },
+            Action::TransposeChars => self.buffer.transpose(),
+            Action::ClearScreen => {
+                let mut stdout = io::stdout();
+                stdout.queue(terminal::Clear(ClearType::All))?;
+                stdout.queue(cursor::MoveTo(0, 0))?;
+                stdout.queue(style::Print(&self.prompt))?;
+                self.cursor_row = 0;
+                true
+            }
+            Action::YankLastArg => self.yank_last_arg(last_action).await,
+            Action::PreviousHistory => self.move_in_history(true).await,
+            Action::NextHistory => self.move_in_history(false).await,
+            Action::ViMovementMode => self.mode == Mode::ViInsert && self.vi_movement_mode()?,
+            Action::EditAndExecuteCommand => return self.edit_and_execute(),
+            Action::ReverseSearchHistory => return self.incremental_search(true).await,
+            Action::ForwardSearchHistory => return self.incremental_search(false).await,
+            Action::Complete => return self.complete_word().await,
+            Action::FuzzyHistory => return self.fuzzy_find(fuzzy::Source::History).await,
+            Action::FuzzyFile => return self.fuzzy_find(fuzzy::Source::Files).await,
+            Action::FuzzyDirectory => return self.fuzzy_find(fuzzy::Source::Directories).await,
+        };
+        if changed {
+            self.refresh()?;
+        }
+        Ok(None)
+    }
+
+    /// 上下键：在以离开时输入的文本开头的历史命令中移动，跳过与当前行相同的命令，
+    /// 回到最底部时恢复正在输入的行
+    async fn move_in_history(&mut self, older: bool) -> bool {
+        if self.history_index == 0 {
+            if !older {
+                return false;
+            }
+            self.history_prefix = self.buffer.text().to_string();
+        }
+        let entries: Vec<String> = history::History::all().await.into_iter().rev().collect();
+        let next = next_history_index(
+            &entries,
+            self.history_index,
+            &self.history_prefix,
+            self.buffer.text(),
+            older,
+        );
+        match next {
+            Some(index) => {
+                if self.history_index == 0 {
+                    self.saved_line = self.buffer.text().to_string();
+                }
+                self.history_index = index;
+                self.buffer.set(&entries[index - 1]);
+            }
+            None if older => return false,
+            None => {
+                self.history_index = 0;
+                self.buffer.set(&std::mem::take(&mut self.saved_line));
+            }
+        }
+        true
+    }
+
+    /// 删除 `start..end` 并放入 kill ring，紧接着上一次删除时合并为一条
+    fn kill(&mut self, start: usize, end: usize, last_action: LastAction) -> bool {
+        if start >= end {
+            self.last_action = last_action;
+            return false;
+        }
+        let backward = end == self.buffer.cursor();
+        let text = self.buffer.remove_range(start, end);
+        kill_ring::kill(&text, backward, last_action == LastAction::Kill);
+        self.last_action = LastAction::Kill;
+        true
+    }
+
+    /// 在光标处插入文本，并记录插入的范围以便下一次按键替换
+    fn insert_recorded(&mut self, text: &str, record: impl Fn(usize, usize) -> LastAction) {
+        let start = self.buffer.cursor();
+        self.buffer.insert(text);
+        self.last_action = record(start, self.buffer.cursor());
+    }
+
+    /// `Alt-.`：插入上一条命令的最后一个参数，连续按下时依次换成更早的命令
+    async fn yank_last_arg(&mut self, last_action: LastAction) -> bool {
+        let index = match last_action {
+            LastAction::LastArg { start, end, index } => {
+                self.buffer.remove_range(start, end);
+                index + 1
+            }
+            _ => 0,
+        };
+        let argument = history::History::get_by_index(index)
+            .await
+            .and_then(|command| last_argument(&command).map(str::to_string));
+        match argument {
+            Some(argument) => {
+                self.insert_recorded(&argument, |start, end| LastAction::LastArg {
+                    start,
+                    end,
+                    index,
+                });
+                true
+            }
+            // 没有更早的命令时保持原样，再按一次从头开始
+            None => index > 0,
+        }
+    }
+
+    /// 光标前的单词是缩写时就地展开
+    fn expand_abbreviation(&mut self) {
+        if let Some((expanded, cursor)) =
+            abbr::expand_before_cursor(self.buffer.text(), self.buffer.cursor())
+        {
+            self.buffer.set_with_cursor(expanded, cursor);
+        }
+    }
+
+    /// 从提示符之后重绘整行，再把光标移到按显示宽度计算的位置
+    fn refresh(&mut self) -> io::Result<()> {
+        let text = visible(self.buffer.text()).into_owned();
+        let (width, position) = (self.buffer.width(), self.buffer.width_before_cursor());
+        self.draw(self.prompt_width as usize, &text, width, position)
+    }
+
+    /// 连同提示符重绘，用于替换了提示符的搜索结束之后
+    fn redisplay(&mut self) -> io::Result<()> {
+        let line = format!("{}{}", self.prompt, visible(self.buffer.text()));
+        let start = self.prompt_width as usize;
+        let (width, position) = (self.buffer.width(), self.buffer.width_before_cursor());
+        self.draw(0, &line, start + width, start + position)
+    }
+
+    /// 从提示符所在行的第 `start` 列开始输出 `content`（可以带颜色），
+    /// `width` 与 `position` 是输出内容与光标之前部分的显示宽度
+    fn draw(
+        &mut self,
+        start: usize,
+        content: &str,
+        width: usize,
+        position: usize,
+    ) -> io::Result<()> {
+        let columns = terminal::size().map_or(80, |(columns, _)| columns.max(1)) as usize;
+        let mut stdout = io::stdout();
+        if self.cursor_row > 0 {
+            stdout.queue(cursor::MoveUp(self.cursor_row))?;
+        }
+        stdout.queue(cursor::MoveToColumn(start as u16))?;
+        stdout.queue(terminal::Clear(ClearType::FromCursorDown))?;
+        stdout.queue(style::Print(content))?;
+
+        let end = start + width;
+        // 恰好写满一行时终端停在行尾，先换到下一行以便计算位置
+        if end > 0 && end.is_multiple_of(columns) {
+            stdout.queue(style::Print("\r\n"))?;
+        }
+        let position = start + position;
+        let end_row = end / columns;
+        let row = position / columns;
+        if end_row > row {
+            stdout.queue(cursor::MoveUp((end_row - row) as u16))?;
+        }
+        stdout.queue(cursor::MoveToColumn((position % columns) as u16))?;
+        stdout.flush()?;
+        self.cursor_row = row as u16;
+        Ok(())
+    }
+
+    /// 把当前行写入临时文件交给 `$VISUAL` 或 `$EDITOR` 编辑，编辑器正常退出后执行编辑结果
+    fn edit_and_execute(&mut self) -> io::Result<Option<String>> {
+        let file = EditFile::create()?;
+        let path = file.path.clone();
+        writeln!(&file.file, "{}", self.buffer.text())?;
+        self.finish("")?;
+        terminal::disable_raw_mode()?;
+        let editor = var::get("VISUAL")
+            .or_else(|| var::get("EDITOR"))
+            .unwrap_or_else(|| "vi".to_string());
+        let mut words = editor.split_whitespace();
+        let status = std::process::Command::new(words.next().unwrap_or("vi"))
+            .args(words)
+            .arg(&path)
+            .status();
+        let edited = std::fs::read_to_string(&path);
+        drop(file);
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(_) => return Ok(Some(String::new())),
+            Err(e) => {
+                println_error!("{}: {}", editor, e);
+                return Ok(Some(String::new()));
+            }
+        }
+        let command = edited?.trim_end_matches('\n').to_string();
+        println!("{}", command);
+        Ok(Some(command))
+    }
+
+    /// 光标移到行尾，输出 `marker` 后换行
+    fn finish(&mut self, marker: &str) -> io::Result<()> {
+        self.buffer.move_end();
+        self.refresh()?;
+        if self.mode != Mode::Emacs {
+            io::stdout().queue(cursor::SetCursorStyle::DefaultUserShape)?;
+        }
+        print!("{}\r\n", marker);
+        io::stdout().flush()
+    }
+}
+
+/// `entries` 从新到旧排列，`index` 是当前的位置（从 1 开始，0 表示正在输入的行）；
+/// 返回 `older` 方向上下一条以 `prefix` 开头且与 `current` 不同的命令的位置
+fn next_history_index(
+    entries: &[String],
+    index: usize,
+    prefix: &str,
+    current: &str,
+    older: bool,
+) -> Option<usize> {
+    let accept = |i: &usize| entries[i - 1].starts_with(prefix) && entries[i - 1] != current;
+    if older {
+        (index + 1..=entries.len()).find(accept)
+    } else {
+        (1..index.min(entries.len() + 1)).rev().find(accept)
+    }
+}
+
+/// 命令行的最后一个单词，引号中的空白不分隔单词
+fn last_argument(line: &str) -> Option<&str> {
+    let mut start = None;
+    let mut last = None;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => escaped = true,
+            (Some(_), _) => {}
+            (None, c) if c.is_whitespace() || matches!(c, ';' | '|' | '&') => {
+                if let Some(start) = start.take() {
+                    last = Some((start, i));
+                }
+            }
+            (None, c) => {
+                start.get_or_insert(i);
+                match c {
+                    '\'' | '"' => quote = Some(c),
+                    '\\' => escaped = true,
+                    _ => {}
+                }
+            }
+        }
+    }
+    if let Some(start) = start {
+        last = Some((start, line.len()));
+    }
+    last.map(|(start, end)| &line[start..end])
+}
+
+/// 插入到命令行的单词：只含普通字符时原样返回，否则加上单引号
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-+./:,=@%^".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        alias::quote(word)
+    }
+}
+
+/// 从非终端的标准输入读取一条命令，行尾的反斜杠表示续行
+fn read_plain_command() -> io::Result<String> {
+    let mut command = String::new();
+    loop {
+        let Some(line) = read_plain_line()? else {
+            if command.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "EOF"));
+            }
+            return Ok(command);
+        };
+        match line.strip_suffix('\\') {
+            Some(stripped) => command.push_str(stripped),
+            None => {
+                command.push_str(&line);
+                return Ok(command);
+            }
+        }
+    }
+}
+
+/// 逐字节读取一行，避免预读走属于子进程的输入（例如 `printf 'cat\nhello\n' | sh-rs`）
+pub(crate) fn read_plain_line() -> io::Result<Option<String>> {
+    let mut bytes = Vec::new();
+    let mut byte = 0u8;
+    loop {
+        let n = unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) };
+        match n {
+            0 if bytes.is_empty() => return Ok(None),
+            0 => break,
+            n if n < 0 => {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            _ if byte == b'\n' => break,
+            _ => bytes.push(byte),
+        }
+    }
+    if bytes.last() == Some(&b'\r') {
+        bytes.pop();
+    }
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// `edit-and-execute-command` 的临时文件：名称不可预测，以 `create_new` 创建，
+/// 不会跟随已存在的符号链接，释放时删除
+struct EditFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl EditFile {
+    fn create() -> io::Result<Self> {
+        loop {
+            let seed = RandomState::new().build_hasher().finish();
+            let path = std::env::temp_dir().join(format!(
+                "sh-rs-edit-{}-{:016x}.sh",
+                std::process::id(),
+                seed
+            ));
+            match File::options()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path)
+            {
+                Ok(file) => return Ok(EditFile { path, file }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for EditFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_history_index() {
+        let entries: Vec<String> = ["git push", "ls", "git push", "git pull", "git push"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(next_history_index(&entries, 0, "git", "git", true), Some(1));
+        // 跳过与当前行相同的命令
+        assert_eq!(
+            next_history_index(&entries, 1, "git", "git push", true),
+            Some(4)
+        );
+        assert_eq!(
+            next_history_index(&entries, 4, "git", "git pull", true),
+            Some(5)
+        );
+        assert_eq!(
+            next_history_index(&entries, 5, "git", "git push", true),
+            None
+        );
+        assert_eq!(
+            next_history_index(&entries, 5, "git", "git push", false),
+            Some(4)
+        );
+        assert_eq!(next_history_index(&entries, 1, "", "git push", false), None);
+        assert_eq!(next_history_index(&entries, 0, "l", "l", true), Some(2));
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("src/main.rs"), "src/main.rs");
+        assert_eq!(shell_quote("my file"), "'my file'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn test_last_argument() {
+        assert_eq!(last_argument("ls -l /tmp"), Some("/tmp"));
+        assert_eq!(last_argument("echo 'a b' \"c d\"  "), Some("\"c d\""));
+        assert_eq!(last_argument("cat a\\ b"), Some("a\\ b"));
+        assert_eq!(last_argument("make && ./run;"), Some("./run"));
+        assert_eq!(last_argument("   "), None);
+    }
+}
@@ -0,0 +1,254 @@
+use super::Editor;
+use super::buffer::{boundary_at_or_before, display_width, visible};
+use super::keymap::{self, Action, Binding, Key, Keymap, Lookup};
+use crate::history::History;
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use lazy_static::lazy_static;
+use std::io;
+use std::sync::Mutex;
+
+lazy_static! {
+    /// 上一次搜索的文本，在空的搜索中再按 `Ctrl-R` 时重新使用
+    static ref LAST_QUERY: Mutex<String> = Mutex::new(String::new());
+}
+
+/// 搜索的一步：搜索文本、匹配的行号与匹配位置；`failed` 时保留上一次成功匹配的行
+#[derive(Clone)]
+struct State {
+    query: String,
+    index: usize,
+    position: usize,
+    failed: bool,
+    older: bool,
+}
+
+/// 从 `lines[from]` 开始查找包含 `query` 的行，`older` 时向下标增大（更早）的方向查找；
+/// 跳过与 `skip` 相同的行，返回行号与匹配位置
+fn find(
+    lines: &[String],
+    query: &str,
+    from: usize,
+    older: bool,
+    skip: Option<&str>,
+) -> Option<(usize, usize)> {
+    let candidates: Box<dyn Iterator<Item = usize>> = if older {
+        Box::new(from..lines.len())
+    } else {
+        Box::new((0..=from.min(lines.len().checked_sub(1)?)).rev())
+    };
+    for i in candidates {
+        if skip == Some(lines[i].as_str()) {
+            continue;
+        }
+        let position = if older {
+            lines[i].rfind(query)
+        } else {
+            lines[i].find(query)
+        };
+        if let Some(position) = position {
+            return Some((i, position));
+        }
+    }
+    None
+}
+
+/// 从 `from` 开始查找 `state.query`，找不到时保留上一次的匹配并标记为失败
+fn step(lines: &[String], state: &mut State, from: usize, skip: Option<&str>) {
+    match find(lines, &state.query, from, state.older, skip) {
+        Some((index, position)) => {
+            state.index = index;
+            state.position = position;
+            state.failed = false;
+        }
+        None => state.failed = true,
+    }
+}
+
+/// 搜索的行：第 0 行是开始上下移动前正在输入的行，第 n 行是倒数第 n 条历史命令，与 `history_index` 一致；
+/// 当前所在的行换成编辑器中的文本，这样搜索从编辑过的文本开始，取消时也不会丢掉修改
+fn search_lines(
+    current: &str,
+    saved: &str,
+    history: Vec<String>,
+    history_index: usize,
+) -> (Vec<String>, usize) {
+    let mut lines = vec![if history_index == 0 { current } else { saved }.to_string()];
+    lines.extend(history);
+    let index = history_index.min(lines.len() - 1);
+    lines[index] = current.to_string();
+    (lines, index)
+}
+
+impl Editor {
+    /// 增量搜索历史命令：输入的文本在提示符的位置显示，`Ctrl-R`/`Ctrl-S` 跳到下一个匹配，
+    /// 退格撤销上一步，`Ctrl-G` 恢复原来的行，`Esc` 与 `Ctrl-J` 结束搜索，其他按键结束搜索后照常处理
+    pub(super) async fn incremental_search(&mut self, older: bool) -> io::Result<Option<String>> {
+        let history = History::all().await.into_iter().rev().collect();
+        let (lines, index) = search_lines(
+            self.buffer.text(),
+            &self.saved_line,
+            history,
+            self.history_index,
+        );
+        let original = (
+            self.buffer.text().to_string(),
+            self.buffer.cursor(),
+            self.history_index,
+        );
+        let mut state = State {
+            query: String::new(),
+            index,
+            position: boundary_at_or_before(&lines[index], self.buffer.cursor()),
+            failed: false,
+            older,
+        };
+        let mut steps: Vec<State> = Vec::new();
+        loop {
+            self.draw_search(&lines[state.index], &state)?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            let plain = !key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+            let action = match keymap::lookup(Keymap::of(self.mode), &[Key::from(&key)]) {
+                Lookup::Bound(Binding::Action(action)) => Some(action),
+                _ => None,
+            };
+            match (key.code, action) {
+                (
+                    _,
+                    Some(action @ (Action::ReverseSearchHistory | Action::ForwardSearchHistory)),
+                ) => {
+                    steps.push(state.clone());
+                    state.older = action == Action::ReverseSearchHistory;
+                    let (from, skip) = if state.query.is_empty() {
+                        state.query = LAST_QUERY.lock().unwrap().clone();
+                        (state.index, None)
+                    } else if state.older {
+                        (state.index + 1, Some(lines[state.index].as_str()))
+                    } else {
+                        match state.index.checked_sub(1) {
+                            Some(from) => (from, Some(lines[state.index].as_str())),
+                            None => {
+                                state.failed = true;
+                                continue;
+                            }
+                        }
+                    };
+                    step(&lines, &mut state, from, skip);
+                }
+                (KeyCode::Backspace, _) => {
+                    if let Some(previous) = steps.pop() {
+                        state = previous;
+                    }
+                }
+                (KeyCode::Char('g'), _) if ctrl => {
+                    let (text, cursor, history_index) = original;
+                    self.buffer.set_with_cursor(text, cursor);
+                    self.history_index = history_index;
+                    self.redisplay()?;
+                    return Ok(None);
+                }
+                (KeyCode::Char(c), _) if plain => {
+                    steps.push(state.clone());
+                    state.query.push(c);
+                    let from = state.index;
+                    step(&lines, &mut state, from, None);
+                }
+                (code, _) => {
+                    let terminator = code == KeyCode::Esc || ctrl && code == KeyCode::Char('j');
+                    if !terminator {
+                        self.replay = Some(key);
+                    }
+                    if !state.query.is_empty() {
+                        *LAST_QUERY.lock().unwrap() = state.query.clone();
+                    }
+                    self.buffer
+                        .set_with_cursor(lines[state.index].clone(), state.position);
+                    // 从搜索到的命令继续上下移动时不再按前缀过滤
+                    if self.history_index == 0 && state.index > 0 {
+                        self.saved_line = lines[0].clone();
+                    }
+                    self.history_prefix.clear();
+                    self.history_index = state.index;
+                    self.redisplay()?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// 在提示符的位置显示 `(reverse-i-search)`query': ` 与匹配的行，匹配的部分反色显示
+    fn draw_search(&mut self, line: &str, state: &State) -> io::Result<()> {
+        let prompt = format!(
+            "({}{})`{}': ",
+            if state.failed { "failed " } else { "" },
+            if state.older {
+                "reverse-i-search"
+            } else {
+                "i-search"
+            },
+            state.query
+        );
+        let start = boundary_at_or_before(line, state.position);
+        let end = if line[start..].starts_with(state.query.as_str()) {
+            start + state.query.len()
+        } else {
+            start
+        };
+        let content = format!(
+            "{}{}{}{}",
+            prompt,
+            visible(&line[..start]),
+            visible(&line[start..end]).reversed(),
+            visible(&line[end..])
+        );
+        let prompt_width = display_width(&prompt);
+        self.draw(
+            0,
+            &content,
+            prompt_width + display_width(line),
+            prompt_width + display_width(&line[..start]),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_find() {
+        let lines: Vec<String> = ["", "git push", "ls", "git commit -m git", "git push"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(find(&lines, "git", 0, true, None), Some((1, 0)));
+        assert_eq!(find(&lines, "git", 2, true, None), Some((3, 14)));
+        // 跳过与当前匹配相同的行
+        assert_eq!(find(&lines, "push", 2, true, Some("git push")), None);
+        assert_eq!(
+            find(&lines, "git", 4, false, Some("git push")),
+            Some((3, 0))
+        );
+        assert_eq!(find(&lines, "zzz", 0, true, None), None);
+    }
+
+    #[test]
+    fn test_search_lines_keep_edits() {
+        // 上移到 `é1` 后在行首插入 `a`，光标停在 `a` 之后
+        let history = vec!["é1".to_string(), "ls".to_string()];
+        let (lines, index) = search_lines("aé1", "draft", history, 1);
+        assert_eq!(index, 1);
+        assert_eq!(lines, vec!["draft", "aé1", "ls"]);
+        assert_eq!(boundary_at_or_before(&lines[index], 1), 1);
+        // 光标在多字节字符中间时退回字符的开头
+        assert_eq!(boundary_at_or_before("é1", 1), 0);
+        // 空的搜索按 `Esc` 后保留编辑过的文本，而不是原来的历史命令
+        let (lines, index) = search_lines("é", "", vec!["ls".to_string()], 0);
+        assert_eq!((lines[index].as_str(), index), ("é", 0));
+    }
+}
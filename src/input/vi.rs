@@ -0,0 +1,840 @@
+use super::keymap::{Action, Mode};
+use super::{Editor, kill_ring};
+use crossterm::{
+    QueueableCommand,
+    cursor::SetCursorStyle,
+    event::{KeyCode, KeyEvent, KeyModifiers},
+};
+use std::io::{self, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// 普通模式下的光标移动
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Motion {
+    Left,
+    Right,
+    /// `w` 与 `W`，参数表示以空白分隔的大单词
+    WordStart(bool),
+    /// `b` 与 `B`
+    WordBackward(bool),
+    /// `e` 与 `E`
+    WordEnd(bool),
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    Find(Find),
+    /// `;` 与 `,`，参数表示反方向
+    RepeatFind(bool),
+}
+
+/// `f`、`t`、`F`、`T` 查找的字符与方向
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Find {
+    pub forward: bool,
+    pub till: bool,
+    pub target: char,
+    /// 由 `;` 或 `,` 重复：`t`、`T` 跳过紧挨着光标的目标字符，否则光标停在原地
+    pub repeat: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+/// 操作符作用的范围
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Motion(Motion),
+    /// `iw`、`a"`、`i(` 等文本对象
+    Object {
+        around: bool,
+        kind: char,
+    },
+    /// `dd`、`cc`、`yy`：整行
+    Line,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Move(Motion),
+    Operate(Operator, Target),
+    /// `p` 与 `P`
+    Put {
+        after: bool,
+    },
+    Replace(char),
+    ToggleCase,
+    /// `i`、`a`、`I`、`A`
+    Insert(char),
+    Undo,
+    Repeat,
+    /// `v`：在 `$VISUAL` 或 `$EDITOR` 中编辑
+    Edit,
+    /// `k` 与 `j`，参数表示更早的命令
+    History(bool),
+}
+
+impl Command {
+    /// 修改文本的命令可以撤销，也可以用 `.` 重复
+    fn is_change(self) -> bool {
+        match self {
+            Command::Operate(operator, _) => operator != Operator::Yank,
+            Command::Put { .. }
+            | Command::Replace(_)
+            | Command::ToggleCase
+            | Command::Insert(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// 普通模式的一条完整命令，`count` 是命令前与操作符后两个次数之积
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parsed {
+    pub count: Option<usize>,
+    pub command: Command,
+}
+
+/// 一次可以用 `.` 重复的修改，包括随后在插入模式中输入的文本
+#[derive(Debug, Clone)]
+struct Change {
+    count: Option<usize>,
+    command: Command,
+    inserted: String,
+}
+
+/// vi 模式在一次读取命令期间的状态
+#[derive(Debug, Default)]
+pub struct ViState {
+    /// 尚未构成完整命令的按键
+    pending: String,
+    last_find: Option<Find>,
+    last_change: Option<Change>,
+    /// 正在插入模式中记录的修改，回到普通模式时成为 `last_change`
+    recording: Option<Change>,
+    /// 每次修改前的文本与光标
+    undo: Vec<(String, usize)>,
+}
+
+impl ViState {
+    /// 记录插入模式中输入的字符，`None` 表示退格
+    pub fn record_insert(&mut self, c: Option<char>) {
+        if let Some(change) = &mut self.recording {
+            match c {
+                Some(c) => change.inserted.push(c),
+                None => {
+                    change.inserted.pop();
+                }
+            }
+        }
+    }
+}
+
+/// 可以跟在 `i` 或 `a` 之后的文本对象
+const OBJECTS: &str = "wW\"'`()b[]{}B<>";
+
+/// 解析普通模式的按键序列；按键还不完整时返回 `Ok(None)`，无法识别时返回 `Err`
+pub fn parse(keys: &str) -> Result<Option<Parsed>, ()> {
+    use Command::*;
+    use Operator::*;
+    let mut chars = keys.chars().peekable();
+    let mut count = read_count(&mut chars);
+    let Some(c) = chars.next() else {
+        return Ok(None);
+    };
+    let command = match c {
+        'd' | 'c' | 'y' => {
+            let operator = match c {
+                'd' => Delete,
+                'c' => Change,
+                _ => Yank,
+            };
+            if let Some(inner) = read_count(&mut chars) {
+                count = Some(count.unwrap_or(1).saturating_mul(inner));
+            }
+            let target = match chars.next() {
+                None => return Ok(None),
+                Some(m) if m == c => Target::Line,
+                Some(m @ ('i' | 'a')) => match chars.next() {
+                    None => return Ok(None),
+                    Some(kind) if OBJECTS.contains(kind) => Target::Object {
+                        around: m == 'a',
+                        kind,
+                    },
+                    Some(_) => return Err(()),
+                },
+                Some(m) => match motion(m, &mut chars)? {
+                    Some(motion) => Target::Motion(motion),
+                    None => return Ok(None),
+                },
+            };
+            Operate(operator, target)
+        }
+        'x' => Operate(Delete, Target::Motion(Motion::Right)),
+        'X' => Operate(Delete, Target::Motion(Motion::Left)),
+        's' => Operate(Change, Target::Motion(Motion::Right)),
+        'S' => Operate(Change, Target::Line),
+        'C' => Operate(Change, Target::Motion(Motion::LineEnd)),
+        'D' => Operate(Delete, Target::Motion(Motion::LineEnd)),
+        'Y' => Operate(Yank, Target::Line),
+        'p' => Put { after: true },
+        'P' => Put { after: false },
+        'r' => match chars.next() {
+            Some(c) => Replace(c),
+            None => return Ok(None),
+        },
+        '~' => ToggleCase,
+        'i' | 'a' | 'I' | 'A' => Insert(c),
+        'u' => Undo,
+        '.' => Repeat,
+        'v' => Edit,
+        'k' | '-' => History(true),
+        'j' | '+' => History(false),
+        c => match motion(c, &mut chars)? {
+            Some(motion) => Move(motion),
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(Parsed { count, command }))
+}
+
+/// 次数以 1-9 开头，单独的 `0` 是移动到行首
+fn read_count(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut count: Option<usize> = None;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        if digit == 0 && count.is_none() {
+            break;
+        }
+        count = Some(count.unwrap_or(0).saturating_mul(10) + digit as usize);
+        chars.next();
+    }
+    count
+}
+
+fn motion(c: char, chars: &mut Peekable<Chars>) -> Result<Option<Motion>, ()> {
+    use Motion::*;
+    let motion = match c {
+        'h' => Left,
+        'l' | ' ' => Right,
+        'w' | 'W' => WordStart(c == 'W'),
+        'b' | 'B' => WordBackward(c == 'B'),
+        'e' | 'E' => WordEnd(c == 'E'),
+        '0' => LineStart,
+        '^' => FirstNonBlank,
+        '$' => LineEnd,
+        ';' => RepeatFind(false),
+        ',' => RepeatFind(true),
+        'f' | 't' | 'F' | 'T' => match chars.next() {
+            Some(target) => Find(self::Find {
+                forward: c.is_lowercase(),
+                till: c == 't' || c == 'T',
+                target,
+                repeat: false,
+            }),
+            None => return Ok(None),
+        },
+        _ => return Err(()),
+    };
+    Ok(Some(motion))
+}
+
+/// 字素簇的类别：0 为空白，1 为单词字符（字母、数字与下划线），2 为其他符号；
+/// 大单词只区分空白与非空白
+fn class(grapheme: &str, big: bool) -> u8 {
+    let c = grapheme.chars().next().unwrap_or(' ');
+    if c.is_whitespace() {
+        0
+    } else if big || c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// 文本中每个字素簇的起始位置与类别
+fn cells(text: &str, big: bool) -> Vec<(usize, u8)> {
+    text.grapheme_indices(true)
+        .map(|(i, grapheme)| (i, class(grapheme, big)))
+        .collect()
+}
+
+/// 第 `index` 个字素簇的起始位置，越界时为文本末尾
+fn offset(text: &str, cells: &[(usize, u8)], index: usize) -> usize {
+    cells.get(index).map_or(text.len(), |cell| cell.0)
+}
+
+/// 光标所在字素簇的序号
+fn cell_index(cells: &[(usize, u8)], cursor: usize) -> usize {
+    cells
+        .iter()
+        .position(|&(start, _)| start >= cursor)
+        .unwrap_or(cells.len())
+}
+
+fn next_boundary(text: &str, index: usize) -> usize {
+    index + text[index..].graphemes(true).next().map_or(0, str::len)
+}
+
+/// 向右的 `e`、`f`、`t` 与 `$` 作为操作符的范围时包括目标字符
+fn inclusive(motion: Motion) -> bool {
+    matches!(
+        motion,
+        Motion::WordEnd(_) | Motion::LineEnd | Motion::Find(Find { forward: true, .. })
+    )
+}
+
+/// 从 `cursor` 按 `motion` 移动 `count` 次后的位置，无法移动时返回 `None`
+fn motion_target(text: &str, cursor: usize, motion: Motion, count: usize) -> Option<usize> {
+    use Motion::*;
+    let big = matches!(motion, WordStart(true) | WordBackward(true) | WordEnd(true));
+    let cells = cells(text, big);
+    let n = cells.len();
+    let class = |i: usize| cells[i].1;
+    let mut i = cell_index(&cells, cursor);
+    match motion {
+        Left => i = i.checked_sub(1)?.saturating_sub(count - 1),
+        Right if i >= n => return None,
+        Right => i = (i + count).min(n),
+        WordStart(_) => {
+            for _ in 0..count {
+                if i < n && class(i) != 0 {
+                    let current = class(i);
+                    while i < n && class(i) == current {
+                        i += 1;
+                    }
+                }
+                while i < n && class(i) == 0 {
+                    i += 1;
+                }
+            }
+        }
+        WordBackward(_) => {
+            for _ in 0..count {
+                while i > 0 && class(i - 1) == 0 {
+                    i -= 1;
+                }
+                if i > 0 {
+                    let current = class(i - 1);
+                    while i > 0 && class(i - 1) == current {
+                        i -= 1;
+                    }
+                }
+            }
+        }
+        WordEnd(_) => {
+            for _ in 0..count {
+                i += 1;
+                while i < n && class(i) == 0 {
+                    i += 1;
+                }
+                if i >= n {
+                    return None;
+                }
+                let current = class(i);
+                while i + 1 < n && class(i + 1) == current {
+                    i += 1;
+                }
+            }
+        }
+        LineStart => i = 0,
+        FirstNonBlank => i = cells.iter().position(|cell| cell.1 != 0).unwrap_or(n),
+        LineEnd => i = n,
+        Find(find) => {
+            let target = find.target.to_string();
+            let is_target = |j: &usize| {
+                let end = offset(text, &cells, j + 1);
+                text[cells[*j].0..end] == target
+            };
+            // 与 vim 相同，只在不带次数重复时跳过
+            let skip = usize::from(find.repeat && find.till && count == 1);
+            for _ in 0..count {
+                let found = if find.forward {
+                    (i + 1 + skip..n).find(is_target)?
+                } else {
+                    (0..i.saturating_sub(skip)).rev().find(is_target)?
+                };
+                i = match (find.till, find.forward) {
+                    (true, true) => found - 1,
+                    (true, false) => found + 1,
+                    (false, _) => found,
+                };
+            }
+        }
+        RepeatFind(_) => return None,
+    }
+    Some(offset(text, &cells, i))
+}
+
+/// 操作符作用的文本范围
+fn operator_range(
+    text: &str,
+    cursor: usize,
+    operator: Operator,
+    target: Target,
+    count: usize,
+) -> Option<(usize, usize)> {
+    let motion = match target {
+        Target::Line => return Some((0, text.len())),
+        Target::Object { around, kind } => return object_range(text, cursor, around, kind),
+        Target::Motion(motion) => motion,
+    };
+    // `cw` 在单词上时修改到单词结尾为止，不包括其后的空白
+    if let (Operator::Change, Motion::WordStart(big)) = (operator, motion) {
+        let cells = cells(text, big);
+        let mut i = cell_index(&cells, cursor);
+        if i < cells.len() && cells[i].1 != 0 {
+            while i + 1 < cells.len() && cells[i + 1].1 == cells[i].1 {
+                i += 1;
+            }
+            let mut end = offset(text, &cells, i);
+            if count > 1 {
+                end = motion_target(text, end, Motion::WordEnd(big), count - 1)?;
+            }
+            return Some((cursor, next_boundary(text, end)));
+        }
+    }
+    let target = motion_target(text, cursor, motion, count)?;
+    if target < cursor {
+        return Some((target, cursor));
+    }
+    let end = if inclusive(motion) && target < text.len() {
+        next_boundary(text, target)
+    } else {
+        target
+    };
+    Some((cursor, end))
+}
+
+/// 文本对象的范围：`w`/`W` 为单词，引号为同一行中成对的引号，其余为成对的括号
+fn object_range(text: &str, cursor: usize, around: bool, kind: char) -> Option<(usize, usize)> {
+    match kind {
+        'w' | 'W' => {
+            let cells = cells(text, kind == 'W');
+            let n = cells.len();
+            let class = |i: usize| cells[i].1;
+            let i = cell_index(&cells, cursor).min(n.checked_sub(1)?);
+            let current = class(i);
+            let (mut start, mut end) = (i, i + 1);
+            while start > 0 && class(start - 1) == current {
+                start -= 1;
+            }
+            while end < n && class(end) == current {
+                end += 1;
+            }
+            if around {
+                // 单词连同其后的空白，其后没有空白时连同其前的空白；在空白上时连同其后的单词
+                if current == 0 || end < n && class(end) == 0 {
+                    if end < n {
+                        let next = class(end);
+                        while end < n && class(end) == next {
+                            end += 1;
+                        }
+                    }
+                } else {
+                    while start > 0 && class(start - 1) == 0 {
+                        start -= 1;
+                    }
+                }
+            }
+            Some((offset(text, &cells, start), offset(text, &cells, end)))
+        }
+        '"' | '\'' | '`' => {
+            let quotes: Vec<usize> = text.match_indices(kind).map(|(i, _)| i).collect();
+            let pair = quotes.chunks_exact(2).find(|pair| cursor <= pair[1])?;
+            let (open, close) = (pair[0], pair[1]);
+            Some(if around {
+                (open, close + 1)
+            } else {
+                (open + 1, close)
+            })
+        }
+        _ => {
+            let (open, close) = match kind {
+                '(' | ')' | 'b' => (b'(', b')'),
+                '[' | ']' => (b'[', b']'),
+                '{' | '}' | 'B' => (b'{', b'}'),
+                _ => (b'<', b'>'),
+            };
+            let bytes = text.as_bytes();
+            let cursor = cursor.min(bytes.len().checked_sub(1)?);
+            // 向左找未配对的左括号，光标在右括号上时从它之前开始
+            let mut depth = 0;
+            let mut start = None;
+            for i in (0..=cursor).rev() {
+                if bytes[i] == close && i != cursor {
+                    depth += 1;
+                } else if bytes[i] == open {
+                    if depth == 0 {
+                        start = Some(i);
+                        break;
+                    }
+                    depth -= 1;
+                }
+            }
+            let start = start?;
+            let mut depth = 0;
+            let end = (start + 1..bytes.len()).find(|&i| {
+                if bytes[i] == open {
+                    depth += 1;
+                } else if bytes[i] == close {
+                    if depth == 0 {
+                        return true;
+                    }
+                    depth -= 1;
+                }
+                false
+            })?;
+            Some(if around {
+                (start, end + 1)
+            } else {
+                (start + 1, end)
+            })
+        }
+    }
+}
+
+impl Editor {
+    /// 按当前模式设置光标形状：插入模式为竖线，普通模式为方块
+    pub(super) fn show_mode(&self) -> io::Result<()> {
+        let style = match self.mode {
+            Mode::Emacs => return Ok(()),
+            Mode::ViInsert => SetCursorStyle::SteadyBar,
+            Mode::ViNormal => SetCursorStyle::SteadyBlock,
+        };
+        io::stdout().queue(style)?.flush()
+    }
+
+    /// 回到普通模式：结束正在记录的修改，光标左移一格
+    pub(super) fn vi_movement_mode(&mut self) -> io::Result<bool> {
+        if let Some(change) = self.vi.recording.take() {
+            self.vi.last_change = Some(change);
+        }
+        self.mode = Mode::ViNormal;
+        self.show_mode()?;
+        self.buffer.move_left();
+        Ok(true)
+    }
+
+    fn vi_insert_mode(&mut self) -> io::Result<()> {
+        self.mode = Mode::ViInsert;
+        self.show_mode()
+    }
+
+    /// 普通模式下光标不能停在行尾之后
+    pub(super) fn clamp_cursor(&mut self) -> bool {
+        self.mode == Mode::ViNormal
+            && !self.buffer.is_empty()
+            && self.buffer.cursor() == self.buffer.text().len()
+            && self.buffer.move_left()
+    }
+
+    /// 处理普通模式中未绑定的按键；字符累积成完整命令后执行
+    pub(super) async fn vi_command(&mut self, key: KeyEvent) -> io::Result<Option<String>> {
+        let c = match key.code {
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                c
+            }
+            // `Esc` 与其他未绑定的按键取消尚未完成的命令
+            _ => {
+                self.vi.pending.clear();
+                return Ok(None);
+            }
+        };
+        self.vi.pending.push(c);
+        let parsed = match parse(&self.vi.pending) {
+            Ok(None) => return Ok(None),
+            Ok(Some(parsed)) => parsed,
+            Err(()) => {
+                self.vi.pending.clear();
+                return Ok(None);
+            }
+        };
+        self.vi.pending.clear();
+        match parsed.command {
+            Command::Edit => return self.edit_and_execute(),
+            Command::History(previous) => {
+                let action = if previous {
+                    Action::PreviousHistory
+                } else {
+                    Action::NextHistory
+                };
+                for _ in 0..parsed.count.unwrap_or(1) {
+                    self.perform(action).await?;
+                }
+                self.buffer.move_home();
+            }
+            Command::Repeat => {
+                let Some(change) = self.vi.last_change.clone() else {
+                    return Ok(None);
+                };
+                self.vi_execute(parsed.count.or(change.count), change.command, false)?;
+                if self.mode == Mode::ViInsert {
+                    self.buffer.insert(&change.inserted);
+                    self.vi_movement_mode()?;
+                }
+            }
+            command => self.vi_execute(parsed.count, command, true)?,
+        }
+        self.clamp_cursor();
+        self.refresh()?;
+        Ok(None)
+    }
+
+    /// `f`、`t` 记为最近一次查找，`;` 与 `,` 换成最近一次查找
+    fn resolve_find(&mut self, motion: Motion) -> Option<Motion> {
+        match motion {
+            Motion::Find(find) => {
+                self.vi.last_find = Some(find);
+                Some(motion)
+            }
+            Motion::RepeatFind(reverse) => self.vi.last_find.map(|find| {
+                Motion::Find(Find {
+                    forward: find.forward != reverse,
+                    repeat: true,
+                    ..find
+                })
+            }),
+            _ => Some(motion),
+        }
+    }
+
+    /// 执行一条普通模式命令；`record` 为假时是 `.` 在重复上一次修改
+    fn vi_execute(
+        &mut self,
+        count: Option<usize>,
+        command: Command,
+        record: bool,
+    ) -> io::Result<()> {
+        let n = count.unwrap_or(1);
+        let text = self.buffer.text().to_string();
+        let cursor = self.buffer.cursor();
+        if command.is_change() {
+            self.vi.undo.push((text.clone(), cursor));
+            if record {
+                self.vi.recording = Some(Change {
+                    count,
+                    command,
+                    inserted: String::new(),
+                });
+            }
+        }
+        match command {
+            Command::Move(motion) => {
+                if let Some(target) = self
+                    .resolve_find(motion)
+                    .and_then(|motion| motion_target(&text, cursor, motion, n))
+                {
+                    self.buffer.set_cursor(target);
+                }
+            }
+            Command::Operate(operator, target) => {
+                let target = match target {
+                    Target::Motion(motion) => self.resolve_find(motion).map(Target::Motion),
+                    target => Some(target),
+                };
+                if let Some((start, end)) =
+                    target.and_then(|target| operator_range(&text, cursor, operator, target, n))
+                {
+                    kill_ring::kill(&text[start..end], false, false);
+                    if operator == Operator::Yank {
+                        self.buffer.set_cursor(start);
+                    } else {
+                        self.buffer.remove_range(start, end);
+                    }
+                }
+                if operator == Operator::Change {
+                    self.vi_insert_mode()?;
+                }
+            }
+            Command::Put { after } => {
+                if let Some(yanked) = kill_ring::yank() {
+                    if after {
+                        self.buffer.move_right();
+                    }
+                    self.buffer.insert(&yanked.repeat(n));
+                    self.buffer.move_left();
+                }
+            }
+            Command::Replace(c) => {
+                let replaced: Vec<&str> = text[cursor..].graphemes(true).take(n).collect();
+                if replaced.len() == n {
+                    let end = cursor + replaced.concat().len();
+                    let replacement = c.to_string().repeat(n);
+                    let position = cursor + replacement.len() - c.len_utf8();
+                    let text = format!("{}{}{}", &text[..cursor], replacement, &text[end..]);
+                    self.buffer.set_with_cursor(text, position);
+                }
+            }
+            Command::ToggleCase => {
+                let end = text[cursor..]
+                    .grapheme_indices(true)
+                    .nth(n)
+                    .map_or(text.len(), |(i, _)| cursor + i);
+                let toggled: String = text[cursor..end]
+                    .chars()
+                    .map(|c| {
+                        if c.is_uppercase() {
+                            c.to_lowercase().to_string()
+                        } else {
+                            c.to_uppercase().to_string()
+                        }
+                    })
+                    .collect();
+                let position = cursor + toggled.len();
+                let text = format!("{}{}{}", &text[..cursor], toggled, &text[end..]);
+                self.buffer.set_with_cursor(text, position);
+            }
+            Command::Insert(c) => {
+                match c {
+                    'a' => {
+                        self.buffer.move_right();
+                    }
+                    'I' => {
+                        let start = motion_target(&text, cursor, Motion::FirstNonBlank, 1);
+                        self.buffer.set_cursor(start.unwrap_or(0));
+                    }
+                    'A' => self.buffer.move_end(),
+                    _ => {}
+                }
+                self.vi_insert_mode()?;
+            }
+            Command::Undo => {
+                if let Some((text, cursor)) = self.vi.undo.pop() {
+                    self.buffer.set_with_cursor(text, cursor);
+                }
+            }
+            Command::Repeat | Command::Edit | Command::History(_) => {}
+        }
+        if self.mode == Mode::ViNormal {
+            // 没有改变文本的修改不占用撤销记录
+            if command.is_change() && self.buffer.text() == text {
+                self.vi.undo.pop();
+            }
+            if let Some(change) = self.vi.recording.take() {
+                self.vi.last_change = Some(change);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(text: &str, keys: &str, cursor: usize) -> Option<String> {
+        let Ok(Some(Parsed {
+            count,
+            command: Command::Operate(operator, target),
+        })) = parse(keys)
+        else {
+            panic!("{} should parse as an operator", keys);
+        };
+        operator_range(text, cursor, operator, target, count.unwrap_or(1))
+            .map(|(start, end)| text[start..end].to_string())
+    }
+
+    #[test]
+    fn test_vi_parse() {
+        assert_eq!(parse("2d"), Ok(None));
+        assert_eq!(parse("d2f"), Ok(None));
+        assert_eq!(
+            parse("2d3w"),
+            Ok(Some(Parsed {
+                count: Some(6),
+                command: Command::Operate(
+                    Operator::Delete,
+                    Target::Motion(Motion::WordStart(false))
+                ),
+            }))
+        );
+        assert_eq!(
+            parse("0"),
+            Ok(Some(Parsed {
+                count: None,
+                command: Command::Move(Motion::LineStart),
+            }))
+        );
+        assert_eq!(
+            parse("10l").map(|parsed| parsed.and_then(|parsed| parsed.count)),
+            Ok(Some(10))
+        );
+        assert_eq!(parse("di!"), Err(()));
+        assert_eq!(parse("Q"), Err(()));
+    }
+
+    #[test]
+    fn test_vi_motions() {
+        let text = "git commit -m 'fix bug'";
+        assert_eq!(
+            motion_target(text, 0, Motion::WordStart(false), 2),
+            Some(11)
+        );
+        assert_eq!(motion_target(text, 0, Motion::WordStart(true), 3), Some(14));
+        assert_eq!(
+            motion_target(text, 11, Motion::WordBackward(false), 1),
+            Some(4)
+        );
+        assert_eq!(motion_target(text, 4, Motion::WordEnd(false), 1), Some(9));
+        let find = |forward, till| Find {
+            forward,
+            till,
+            target: 'm',
+            repeat: false,
+        };
+        assert_eq!(
+            motion_target(text, 0, Motion::Find(find(true, false)), 2),
+            Some(7)
+        );
+        assert_eq!(
+            motion_target(text, 0, Motion::Find(find(true, true)), 1),
+            Some(5)
+        );
+        assert_eq!(
+            motion_target(text, 12, Motion::Find(find(false, true)), 1),
+            Some(8)
+        );
+        // 光标已在目标字符之前：`t` 不动，`;` 重复时跳过紧挨着的目标
+        let repeat = |forward| Find {
+            repeat: true,
+            ..find(forward, true)
+        };
+        assert_eq!(
+            motion_target(text, 5, Motion::Find(find(true, true)), 1),
+            Some(5)
+        );
+        assert_eq!(
+            motion_target(text, 5, Motion::Find(repeat(true)), 1),
+            Some(6)
+        );
+        assert_eq!(
+            motion_target(text, 8, Motion::Find(repeat(false)), 1),
+            Some(7)
+        );
+        assert_eq!(motion_target(text, 0, Motion::Left, 1), None);
+    }
+
+    #[test]
+    fn test_vi_operator_ranges() {
+        let text = "echo (a \"b c\") done";
+        assert_eq!(range(text, "dw", 0).as_deref(), Some("echo "));
+        assert_eq!(range(text, "cw", 0).as_deref(), Some("echo"));
+        assert_eq!(range(text, "d$", 15).as_deref(), Some("done"));
+        assert_eq!(range(text, "d2f\"", 0).as_deref(), Some("echo (a \"b c\""));
+        assert_eq!(range(text, "di\"", 10).as_deref(), Some("b c"));
+        assert_eq!(range(text, "da(", 10).as_deref(), Some("(a \"b c\")"));
+        assert_eq!(range(text, "diw", 1).as_deref(), Some("echo"));
+        assert_eq!(range(text, "daw", 16).as_deref(), Some(" done"));
+        assert_eq!(range(text, "dd", 3).as_deref(), Some(text));
+        assert_eq!(range(text, "db", 0), Some(String::new()));
+    }
+}
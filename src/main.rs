@@ -1,15 +1,28 @@
+use crate::args::Options;
 use crate::interrupt::sigint_handler;
-use crate::token::parse_command_chain;
+use crate::token::ParseError;
 use std::sync::atomic::{AtomicBool, Ordering};
 
+mod abbr;
+mod alias;
+mod args;
+mod builtin;
+mod complete;
+mod dirstack;
 mod exec;
+mod frecency;
 mod history;
 mod input;
 mod interrupt;
+mod options;
 mod output;
 mod prompt;
+mod resolve;
+mod script;
 mod shrc;
+mod suggest;
 mod token;
+mod var;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -17,40 +30,90 @@ static IS_WAITING_FOR_INPUT: AtomicBool = AtomicBool::new(true);
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    #[cfg(unix)]
-    tokio::task::spawn(sigint_handler());
+    let options = Options::parse(std::env::args().skip(1));
+    if let Some(arg0) = &options.arg0 {
+        var::set_arg0(arg0);
+    }
+    var::replace_positional_args(options.args);
+    builtin::cd::init_pwd();
+
+    let login = options.login
+        || std::env::args()
+            .next()
+            .is_some_and(|arg0| arg0.starts_with('-'));
+    if login && !options.no_profile {
+        shrc::load_profile().await;
+    }
 
-    if let Err(e) = shrc::load_shrc().await {
-        println_error!("Error loading ~/.shrc: {}", e);
+    // 执行 `-c` 命令或脚本文件后以最后的状态退出
+    if let Some(command) = options.command {
+        IS_WAITING_FOR_INPUT.store(false, Ordering::SeqCst);
+        script::run("-c", &command).await;
+        std::process::exit(exec::last_status());
     }
-    if let Err(e) = history::History::load().await {
-        println_error!("Error loading history: {}", e);
+    if let Some(path) = options.script {
+        IS_WAITING_FOR_INPUT.store(false, Ordering::SeqCst);
+        if let Err(e) = script::source(&path, &[]).await {
+            println_error!("sh-rs: {}: {}", path, e);
+            std::process::exit(127);
+        }
+        std::process::exit(exec::last_status());
     }
+
+    // 标准输入不是终端时逐行读取命令，不显示提示符，也不加载启动文件与历史记录
+    let interactive = input::is_interactive();
+    if interactive {
+        #[cfg(unix)]
+        tokio::task::spawn(sigint_handler());
+        frecency::enable();
+
+        if !options.no_rc {
+            shrc::load_rc(options.rc_file.as_deref()).await;
+        }
+        if let Err(e) = history::History::load().await {
+            println_error!("Error loading history: {}", e);
+        }
+    }
+    // 尚未构成完整命令的输入，例如未闭合的 `if` 或引号
+    let mut pending = String::new();
     loop {
-        IS_WAITING_FOR_INPUT.store(true, Ordering::SeqCst);
-        let width = prompt::print_prompt();
-        match input::read_command(width).await {
+        IS_WAITING_FOR_INPUT.store(interactive, Ordering::SeqCst);
+        let (prompt, width) = if pending.is_empty() {
+            prompt::get_prompt()
+        } else {
+            prompt::continuation_prompt()
+        };
+        match input::read_command(&prompt, width).await {
             Ok(input) => {
-                let trimmed_input = input.trim();
-                if trimmed_input.is_empty() {
+                if pending.is_empty() && input.trim().is_empty() {
+                    continue;
+                }
+                pending.push_str(&input);
+                if let Err(ParseError::Incomplete) = token::parse_program(&pending) {
+                    pending.push('\n');
                     continue;
                 }
+                let command = std::mem::take(&mut pending);
+                let trimmed_input = command.trim();
                 IS_WAITING_FOR_INPUT.store(false, Ordering::SeqCst);
-                history::History::save(trimmed_input).await?;
+                if interactive {
+                    history::History::save(trimmed_input).await?;
+                }
 
-                match parse_command_chain(token::tokenize(trimmed_input)) {
-                    Ok(command_parts) => {
-                        if let Err(e) = exec::execute_command_parts(command_parts).await {
-                            println_error!("Execution error: {}", e);
-                        }
-                    }
-                    Err(e) => println_error!("Parse error: {}", e),
+                if let Err(e) = exec::execute_line(trimmed_input).await {
+                    println_error!("{}", e);
                 }
+                exec::take_return();
             }
             Err(e) => {
                 if e.kind() == std::io::ErrorKind::Interrupted {
+                    pending.clear();
                     continue;
                 } else if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                    if !pending.is_empty() {
+                        exec::set_last_status(2);
+                        println_error!("{}", ParseError::Incomplete);
+                    }
                     break;
                 }
                 println_error!("Error reading input: {}", e);
@@ -58,5 +121,8 @@ async fn main() -> Result<()> {
             }
         }
     }
+    if !interactive {
+        std::process::exit(exec::last_status());
+    }
     Ok(())
 }
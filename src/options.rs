@@ -0,0 +1,38 @@
+use lazy_static::lazy_static;
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+
+/// 可以用 `set -o` 或 `shopt -s` 打开的选项
+const NAMES: &[&str] = &["autocd", "emacs", "vi"];
+
+/// 行编辑器的键位，同一时间只能打开其中一个
+const EDITING_MODES: &[&str] = &["emacs", "vi"];
+
+lazy_static! {
+    static ref ENABLED: RwLock<BTreeSet<&'static str>> = RwLock::new(BTreeSet::from(["emacs"]));
+}
+
+pub fn is_set(name: &str) -> bool {
+    ENABLED.read().unwrap().contains(name)
+}
+
+pub fn set(name: &str, on: bool) -> Result<(), String> {
+    let Some(name) = NAMES.iter().find(|known| **known == name) else {
+        return Err(format!("{}: invalid option name", name));
+    };
+    let mut enabled = ENABLED.write().unwrap();
+    if on {
+        if EDITING_MODES.contains(name) {
+            enabled.retain(|enabled| !EDITING_MODES.contains(enabled));
+        }
+        enabled.insert(name);
+    } else {
+        enabled.remove(name);
+    }
+    Ok(())
+}
+
+/// 全部选项及其状态，按名称排序
+pub fn all() -> Vec<(&'static str, bool)> {
+    NAMES.iter().map(|name| (*name, is_set(name))).collect()
+}
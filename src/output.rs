@@ -7,13 +7,13 @@ pub fn print_with_color(message: &str, color: Color) {
 #[macro_export]
 macro_rules! print_error {
     ($($arg:tt)*) => {
-        crate::output::print_with_color(&format!($($arg)*), colored::Color::Red)
+        $crate::output::print_with_color(&format!($($arg)*), colored::Color::Red)
     };
 }
 
 #[macro_export]
 macro_rules! println_error {
     ($($arg:tt)*) => {
-        crate::output::print_with_color(&format!($($arg)*), colored::Color::Red)
+        $crate::output::print_with_color(&format!($($arg)*), colored::Color::Red)
     };
 }
@@ -1,18 +1,16 @@
 use colored::Colorize;
 use std::io::Write;
+use unicode_width::UnicodeWidthStr;
 
 pub fn get_prompt() -> (String, u16) {
     // 获取当前工作目录
-    let current_dir = std::env::current_dir()
-        .unwrap_or_else(|_| std::path::PathBuf::from("?"))
-        .display()
-        .to_string()
-        .blue();
+    let current_dir = crate::builtin::cd::logical_pwd().display().to_string();
+    let width = current_dir.width() + 5;
     let green_prompt = "sh>".green();
     // 构建提示符字符串
     (
-        format!("{} {} ", current_dir, green_prompt),
-        (current_dir.len() + 5) as u16,
+        format!("{} {} ", current_dir.blue(), green_prompt),
+        width as u16,
     )
 }
 
@@ -22,3 +20,8 @@ pub fn print_prompt() -> u16 {
     std::io::stdout().flush().unwrap();
     width
 }
+
+/// 多行命令未输入完整时的续行提示符
+pub fn continuation_prompt() -> (String, u16) {
+    ("> ".to_string(), 2)
+}
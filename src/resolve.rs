@@ -0,0 +1,242 @@
+use crate::{alias, builtin, exec};
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// 保留字，`type` 报告为 keyword
+const KEYWORDS: &[&str] = &[
+    "[[", "]]", "elif", "else", "fi", "function", "if", "then", "{", "}",
+];
+
+/// 命令名解析的结果，按 alias → keyword → function → builtin → 文件 的顺序查找
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    Alias(String),
+    Keyword,
+    Function,
+    Builtin,
+    /// 可执行文件，`hashed` 表示路径来自缓存
+    File {
+        path: PathBuf,
+        hashed: bool,
+    },
+}
+
+struct HashTable {
+    /// 建立缓存时的 `PATH`，`PATH` 改变后缓存失效
+    path: Option<String>,
+    entries: BTreeMap<String, (PathBuf, usize)>,
+}
+
+lazy_static! {
+    static ref HASH: RwLock<HashTable> = RwLock::new(HashTable {
+        path: None,
+        entries: BTreeMap::new(),
+    });
+}
+
+/// 按执行时的顺序解析命令名
+pub fn resolve(name: &str) -> Option<Resolution> {
+    resolve_all(name).into_iter().next()
+}
+
+/// 命令名的全部含义，供 `type -a` 使用；文件包括 `PATH` 中所有同名的可执行文件
+pub fn resolve_all(name: &str) -> Vec<Resolution> {
+    let mut found = Vec::new();
+    if let Some(value) = alias::get(name) {
+        found.push(Resolution::Alias(value));
+    }
+    if KEYWORDS.contains(&name) {
+        found.push(Resolution::Keyword);
+    }
+    if exec::function(name).is_some() {
+        found.push(Resolution::Function);
+    }
+    if builtin::is_builtin(name) && !KEYWORDS.contains(&name) {
+        found.push(Resolution::Builtin);
+    }
+    if name.contains('/') {
+        if is_executable(Path::new(name)) {
+            found.push(Resolution::File {
+                path: PathBuf::from(name),
+                hashed: false,
+            });
+        }
+        return found;
+    }
+    let hashed = hashed_path(name);
+    if let Some(path) = &hashed {
+        found.push(Resolution::File {
+            path: path.clone(),
+            hashed: true,
+        });
+    }
+    for path in search_path_all(name) {
+        if hashed.as_ref() != Some(&path) {
+            found.push(Resolution::File {
+                path,
+                hashed: false,
+            });
+        }
+    }
+    found
+}
+
+/// 外部命令的路径：含 `/` 的名称原样使用，否则先查缓存再搜索 `PATH` 并记入缓存，
+/// `PATH` 中没有可执行文件时退而使用不可执行的普通文件
+pub fn find_command(name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        return Some(PathBuf::from(name));
+    }
+    if let Some(path) = hashed_path(name) {
+        let mut table = HASH.write().unwrap();
+        if let Some((_, hits)) = table.entries.get_mut(name) {
+            *hits += 1;
+        }
+        return Some(path);
+    }
+    // 只有不可执行的同名文件时使用第一个，执行时报告 126（Permission denied）而不是找不到命令；
+    // 这样的路径不记入缓存
+    let Some(path) = search_path(name) else {
+        return search_path_file(name);
+    };
+    let mut table = HASH.write().unwrap();
+    table.path = env::var("PATH").ok();
+    table.entries.insert(name.to_string(), (path.clone(), 1));
+    Some(path)
+}
+
+/// 缓存中仍然有效的路径；`PATH` 改变时清空缓存，文件消失时删除该项
+fn hashed_path(name: &str) -> Option<PathBuf> {
+    let mut table = HASH.write().unwrap();
+    if table.path != env::var("PATH").ok() {
+        table.entries.clear();
+        return None;
+    }
+    let path = table.entries.get(name).map(|(path, _)| path.clone())?;
+    if is_executable(&path) {
+        Some(path)
+    } else {
+        table.entries.remove(name);
+        None
+    }
+}
+
+/// `hash name`：搜索 `PATH` 并记入缓存，不计入命中次数
+pub fn hash(name: &str) -> Option<PathBuf> {
+    let path = search_path(name)?;
+    let mut table = HASH.write().unwrap();
+    if table.path != env::var("PATH").ok() {
+        table.entries.clear();
+        table.path = env::var("PATH").ok();
+    }
+    table.entries.insert(name.to_string(), (path.clone(), 0));
+    Some(path)
+}
+
+/// `hash -r`
+pub fn clear_hash() {
+    HASH.write().unwrap().entries.clear();
+}
+
+/// 缓存的全部命令：名称、路径与命中次数
+pub fn hashed() -> Vec<(String, PathBuf, usize)> {
+    HASH.read()
+        .unwrap()
+        .entries
+        .iter()
+        .map(|(name, (path, hits))| (name.clone(), path.clone(), *hits))
+        .collect()
+}
+
+/// 在 `PATH` 中查找可执行文件，不使用缓存
+pub fn search_path(name: &str) -> Option<PathBuf> {
+    search_path_all(name).into_iter().next()
+}
+
+/// 在 `PATH` 中查找普通文件，不要求可执行，供 `.` 与 `source` 使用
+pub fn search_path_file(name: &str) -> Option<PathBuf> {
+    path_candidates(name)
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+fn search_path_all(name: &str) -> Vec<PathBuf> {
+    path_candidates(name)
+        .into_iter()
+        .filter(|candidate| is_executable(candidate))
+        .collect()
+}
+
+/// `PATH` 中每个目录下的 `name`，按 `PATH` 的顺序
+fn path_candidates(name: &str) -> Vec<PathBuf> {
+    let Some(paths) = env::var_os("PATH") else {
+        return Vec::new();
+    };
+    env::split_paths(&paths).map(|dir| dir.join(name)).collect()
+}
+
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+/// `PATH` 中全部可执行文件的名称
+pub fn path_executables() -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let Some(paths) = env::var_os("PATH") else {
+        return names;
+    };
+    for dir in env::split_paths(&paths) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if is_executable(&entry.path())
+                && let Ok(name) = entry.file_name().into_string()
+            {
+                names.insert(name);
+            }
+        }
+    }
+    names
+}
+
+/// 可以作为命令名的全部名称：别名、函数、内置命令与 `PATH` 中的可执行文件
+pub fn command_names() -> BTreeSet<String> {
+    let mut names: BTreeSet<String> = builtin::BUILTINS.iter().map(|s| s.to_string()).collect();
+    names.extend(alias::all().into_iter().map(|(name, _)| name));
+    names.extend(exec::function_names());
+    names.extend(path_executables());
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_order() {
+        assert_eq!(resolve("if"), Some(Resolution::Keyword));
+        assert_eq!(resolve("cd"), Some(Resolution::Builtin));
+        assert_eq!(resolve("no-such-command-here"), None);
+        crate::alias::set("t_resolve", "echo");
+        assert_eq!(
+            resolve("t_resolve"),
+            Some(Resolution::Alias("echo".to_string()))
+        );
+        let Some(Resolution::File { path, .. }) = resolve("sh") else {
+            panic!("sh should be found in PATH");
+        };
+        assert!(path.ends_with("sh"));
+        assert_eq!(find_command("sh"), Some(path.clone()));
+        assert!(
+            hashed()
+                .iter()
+                .any(|(name, hashed, _)| name == "sh" && *hashed == path)
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::token::{ParseError, parse_program};
+use crate::{exec, println_error, resolve, var};
+use lazy_static::lazy_static;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+lazy_static! {
+    /// 正在执行的脚本位置 `file:line`，用于错误信息
+    static ref LOCATION: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// 错误信息的前缀，交互执行时为空
+pub fn error_prefix() -> String {
+    match LOCATION.lock().unwrap().as_deref() {
+        Some(location) => format!("{}: ", location),
+        None => String::new(),
+    }
+}
+
+/// 是否正在执行脚本、启动文件、`source` 的文件或 `-c` 的命令
+pub fn is_running() -> bool {
+    LOCATION.lock().unwrap().is_some()
+}
+
+/// 执行脚本内容：逐行读入，直到累积的内容构成完整的命令（多行的 `if`、函数、
+/// 引号中的换行、续行符等）后再执行。出错时以 `name:line: message` 的格式报告
+pub async fn run(name: &str, contents: &str) {
+    let mut pending = String::new();
+    let mut start_line = 1;
+    for (index, line) in contents.lines().enumerate() {
+        if pending.is_empty() {
+            start_line = index + 1;
+        }
+        pending.push_str(line);
+        pending.push('\n');
+        match parse_program(&pending) {
+            Err(ParseError::Incomplete) => continue,
+            Err(e) => {
+                exec::set_last_status(2);
+                println_error!("{}:{}: {}", name, start_line, e);
+            }
+            Ok(list) => {
+                let location = format!("{}:{}", name, start_line);
+                let saved = LOCATION.lock().unwrap().replace(location);
+                exec::execute_list(&list).await;
+                *LOCATION.lock().unwrap() = saved;
+            }
+        }
+        pending.clear();
+        if exec::returning() {
+            break;
+        }
+    }
+    if !pending.is_empty() {
+        exec::set_last_status(2);
+        println_error!("{}:{}: {}", name, start_line, ParseError::Incomplete);
+    }
+}
+
+/// 在当前 shell 中执行文件，`path` 原样打开；给出参数时在执行期间替换位置参数
+pub async fn source(path: &str, args: &[String]) -> std::io::Result<()> {
+    let contents = tokio::fs::read_to_string(path).await?;
+    if args.is_empty() {
+        run(path, &contents).await;
+    } else {
+        let saved = var::replace_positional_args(args.to_vec());
+        run(path, &contents).await;
+        var::replace_positional_args(saved);
+    }
+    exec::take_return();
+    Ok(())
+}
+
+/// `.` 与 `source` 的文件查找：不含 `/` 的文件名先在 `PATH` 中查找，找不到时使用当前目录下的文件。
+/// 命令行上的脚本文件不经过查找
+pub fn find_sourced(path: &str) -> PathBuf {
+    if !path.contains('/')
+        && let Some(found) = resolve::search_path_file(path)
+    {
+        return found;
+    }
+    Path::new(path).to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_source_reads_path_as_given() {
+        // `sh` 在 `PATH` 中，但当前目录下没有名为 `sh` 的文件
+        assert!(find_sourced("sh").is_absolute());
+        let error = source("sh", &[]).await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    }
+}
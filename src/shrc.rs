@@ -1,44 +1,76 @@
 use crate::IS_WAITING_FOR_INPUT;
-use crate::token::parse_command_chain;
-use crate::{Result, exec, println_error};
-use std::env::VarError;
+use crate::token::expand_env_vars;
+use crate::{println_error, script};
+use std::env;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::Ordering;
-use tokio::fs;
 
-pub async fn load_shrc() -> Result<()> {
-    match std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")) {
-        Ok(home) => {
-            let shrc_path = format!("{}/.shrc", home);
-            match std::fs::read_to_string(&shrc_path) {
-                Ok(contents) => {
-                    IS_WAITING_FOR_INPUT.store(false, Ordering::SeqCst);
-                    for line in contents.lines() {
-                        let trimmed_line = line.trim();
-                        if trimmed_line.is_empty() || trimmed_line.starts_with('#') {
-                            continue;
-                        }
-                        let tokens = crate::token::tokenize(trimmed_line);
-                        match parse_command_chain(tokens) {
-                            Ok(command_parts) => {
-                                if let Err(e) = exec::execute_command_parts(command_parts).await {
-                                    println_error!("Error executing {}: {}", shrc_path, e);
-                                }
-                            }
-                            Err(e) => println_error!("Parse error in {}: {}", shrc_path, e),
-                        }
-                    }
-                }
-                Err(_) => {
-                    let file = fs::OpenOptions::new()
-                        .create(true)
-                        .write(true)
-                        .open(&shrc_path)
-                        .await?;
-                    drop(file);
+fn home_dir() -> Option<PathBuf> {
+    env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .ok()
+        .map(PathBuf::from)
+}
+
+/// 登录 shell 的启动文件：依次读取 /etc/profile 与 ~/.profile
+pub async fn load_profile() {
+    source_if_exists(Path::new("/etc/profile")).await;
+    if let Some(home) = home_dir() {
+        source_if_exists(&home.join(".profile")).await;
+    }
+}
+
+/// 交互式 shell 的启动文件：`--rcfile` 指定的文件，否则为
+/// `$XDG_CONFIG_HOME/sh-rs/shrc`（默认 `~/.config/sh-rs/shrc`），不存在时回退到 `~/.shrc`。
+/// 随后与 POSIX sh 一样读取 `$ENV` 展开后指向的文件。文件不存在时不会自动创建
+pub async fn load_rc(rc_file: Option<&str>) {
+    match rc_file {
+        Some(path) => {
+            if let Err(e) = source(Path::new(path)).await {
+                println_error!("sh-rs: {}: {}", path, e);
+            }
+        }
+        None => {
+            let candidates = [
+                config_dir().map(|dir| dir.join("shrc")),
+                home_dir().map(|home| home.join(".shrc")),
+            ];
+            for path in candidates.into_iter().flatten() {
+                if path.is_file() {
+                    source_if_exists(&path).await;
+                    break;
                 }
             }
         }
-        Err(_) => return Err(Box::new(VarError::NotPresent)),
     }
-    Ok(())
+
+    if let Ok(env_file) = env::var("ENV") {
+        let path = expand_env_vars(&env_file);
+        if !path.is_empty() {
+            source_if_exists(Path::new(&path)).await;
+        }
+    }
+}
+
+/// sh-rs 的配置目录 `$XDG_CONFIG_HOME/sh-rs`
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()?.join(".config"),
+    };
+    Some(base.join("sh-rs"))
+}
+
+async fn source_if_exists(path: &Path) {
+    if !path.is_file() {
+        return;
+    }
+    if let Err(e) = source(path).await {
+        println_error!("sh-rs: {}: {}", path.display(), e);
+    }
+}
+
+async fn source(path: &Path) -> std::io::Result<()> {
+    IS_WAITING_FOR_INPUT.store(false, Ordering::SeqCst);
+    script::source(&path.display().to_string(), &[]).await
 }
@@ -0,0 +1,83 @@
+use crate::resolve;
+
+/// 最多给出的建议数量
+const MAX_SUGGESTIONS: usize = 3;
+
+/// 编辑距离（Damerau-Levenshtein 的受限版本），相邻字符交换计为一次编辑
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// 与 `name` 编辑距离最小且不超过阈值的命令，短名称只允许一次编辑，单个字符不给出建议
+pub fn suggestions(name: &str) -> Vec<String> {
+    best_matches(name, resolve::command_names())
+}
+
+fn best_matches(name: &str, candidates: impl IntoIterator<Item = String>) -> Vec<String> {
+    let threshold = match name.chars().count() {
+        0..=1 => return Vec::new(),
+        2..=3 => 1,
+        _ => 2,
+    };
+    let mut best = Vec::new();
+    let mut best_distance = threshold + 1;
+    for candidate in candidates {
+        let distance = edit_distance(name, &candidate);
+        if distance == 0 || distance > threshold {
+            continue;
+        }
+        if distance < best_distance {
+            best_distance = distance;
+            best.clear();
+        }
+        if distance == best_distance {
+            best.push(candidate);
+        }
+    }
+    best.truncate(MAX_SUGGESTIONS);
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("git", "git"), 0);
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("sl", "ls"), 1);
+        assert_eq!(edit_distance("grpe", "grep"), 1);
+        assert_eq!(edit_distance("pyhton3", "python3"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_best_matches() {
+        let candidates = ["git", "gist", "grep", "gzip", "ls"].map(String::from);
+        assert_eq!(best_matches("gti", candidates.clone()), vec!["git"]);
+        assert_eq!(best_matches("grpe", candidates.clone()), vec!["grep"]);
+        assert_eq!(best_matches("gisp", candidates.clone()), vec!["gist"]);
+        assert!(best_matches("xyz", candidates).is_empty());
+    }
+}
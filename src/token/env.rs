@@ -1,22 +1,48 @@
+use crate::{dirstack, var};
 use std::env;
 
+/// `${...}` 的展开结果，`[@]` 形式的数组会展开为多个字段
+enum Expansion {
+    Text(String),
+    Fields(Vec<String>),
+}
+
 pub fn expand_env_vars(input: &str) -> String {
+    expand_word(input).join(" ")
+}
+
+/// 展开一个单词，`${arr[@]}` 与 `${!arr[@]}` 的每个元素成为独立的字段
+pub fn expand_word(input: &str) -> Vec<String> {
+    let mut fields = Vec::new();
     let mut out = String::with_capacity(input.len());
+    // 空数组展开后若单词没有其他内容，则整个单词消失
+    let mut produced = false;
     let mut chars = input.chars().peekable();
 
     while let Some(c) = chars.next() {
+        produced = true;
         match c {
             '~' => {
-                if out.is_empty() && let Ok(home) = env::var("HOME").or_else(|_| env::var("USERPROFILE")) {
-                    out.push_str(&home);
-                } else {
-                    out.push('~');
+                // 单词开头的 `~` 前缀一直延伸到第一个 `/`
+                let prefix: String = chars.clone().take_while(|&c| c != '/').collect();
+                match (fields.is_empty() && out.is_empty())
+                    .then(|| expand_tilde(&prefix))
+                    .flatten()
+                {
+                    Some(dir) => {
+                        out.push_str(&dir);
+                        for _ in prefix.chars() {
+                            chars.next();
+                        }
+                    }
+                    None => out.push('~'),
                 }
             }
             '\\' => {
-                if let Some('$') = chars.peek().copied() {
+                // 词法分析阶段转义过的 `$`、`\\`、`~` 按字面输出
+                if let Some(escaped @ ('$' | '\\' | '~')) = chars.peek().copied() {
                     chars.next();
-                    out.push('$');
+                    out.push(escaped);
                 } else {
                     out.push('\\');
                 }
@@ -26,28 +52,51 @@ pub fn expand_env_vars(input: &str) -> String {
                 if let Some('{') = chars.peek().copied() {
                     // ${VAR}
                     chars.next(); // consume '{'
-                    let mut name = String::new();
-                    while let Some(nc) = chars.next() {
-                        if nc == '}' {
-                            break;
+                    let mut content = String::new();
+                    let mut depth = 0;
+                    for nc in chars.by_ref() {
+                        match nc {
+                            '}' if depth == 0 => break,
+                            '}' => depth -= 1,
+                            '{' => depth += 1,
+                            _ => {}
+                        }
+                        content.push(nc);
+                    }
+                    match expand_braced(&content) {
+                        Expansion::Text(val) => out.push_str(&val),
+                        Expansion::Fields(values) => {
+                            produced = !values.is_empty() || !out.is_empty() || !fields.is_empty();
+                            push_fields(&mut fields, &mut out, values);
                         }
-                        name.push(nc);
                     }
-                    let val = env::var(&name).unwrap_or_default();
-                    out.push_str(&val);
+                } else if let Some('?') = chars.peek().copied() {
+                    // $? -> 上一条命令的退出状态
+                    chars.next();
+                    out.push_str(&crate::exec::last_status().to_string());
                 } else if let Some('$') = chars.peek().copied() {
                     // $$ -> PID
                     chars.next(); // consume second '$'
                     let pid = std::process::id();
                     out.push_str(&pid.to_string());
                 } else if let Some(digit @ ('0'..='9')) = chars.peek().copied() {
+                    // $0 -> script name, $1..$9 -> 位置参数
+                    chars.next();
+                    let index = digit.to_digit(10).unwrap_or_default() as usize;
+                    out.push_str(&var::positional(index).unwrap_or_default());
+                } else if let Some('#') = chars.peek().copied() {
+                    // $# -> 位置参数个数
                     chars.next();
-                    let mut script_name = String::new();
-                    // $0 -> script name
-                    if digit == '0' {
-                        script_name = env::args().next().unwrap_or_default();
+                    out.push_str(&var::positional_args().len().to_string());
+                } else if let Some(special @ ('@' | '*')) = chars.peek().copied() {
+                    chars.next();
+                    let args = var::positional_args();
+                    if special == '*' {
+                        out.push_str(&args.join(" "));
+                    } else {
+                        produced = !args.is_empty() || !out.is_empty() || !fields.is_empty();
+                        push_fields(&mut fields, &mut out, args);
                     }
-                    out.push_str(&script_name);
                 } else {
                     // $VAR
                     let mut name = String::new();
@@ -64,7 +113,7 @@ pub fn expand_env_vars(input: &str) -> String {
                                     break;
                                 }
                             }
-                            let val = env::var(&name).unwrap_or_default();
+                            let val = var::get(&name).unwrap_or_default();
                             out.push_str(&val);
                         } else {
                             // Not a valid var name, keep '$'
@@ -80,12 +129,99 @@ pub fn expand_env_vars(input: &str) -> String {
         }
     }
 
-    out
+    if !produced {
+        return fields;
+    }
+    fields.push(out);
+    fields
+}
+
+/// `~`、`~+`、`~-` 以及引用目录栈的 `~N`、`~+N`、`~-N`
+fn expand_tilde(prefix: &str) -> Option<String> {
+    match prefix {
+        "" => env::var("HOME").or_else(|_| env::var("USERPROFILE")).ok(),
+        "+" => var::get("PWD"),
+        "-" => var::get("OLDPWD"),
+        spec => dirstack::get(spec),
+    }
+}
+
+/// 第一个值接在当前字段之后，其余的值各自开始新的字段
+fn push_fields(fields: &mut Vec<String>, out: &mut String, values: Vec<String>) {
+    let mut values = values.into_iter();
+    if let Some(first) = values.next() {
+        out.push_str(&first);
+    }
+    for value in values {
+        fields.push(std::mem::replace(out, value));
+    }
+}
+
+/// 展开 `${...}` 花括号内的内容：
+/// `name`、`name[i]`、`name[@]`、`name[*]`、`#name`、`#name[@]`、`!name[@]`
+fn expand_braced(content: &str) -> Expansion {
+    let (length, keys, body) = match content.as_bytes().first() {
+        Some(b'#') if content.len() > 1 => (true, false, &content[1..]),
+        Some(b'!') if content.len() > 1 => (false, true, &content[1..]),
+        _ => (false, false, content),
+    };
+
+    let (name, subscript) = match body.find('[') {
+        Some(open) if body.ends_with(']') => (&body[..open], Some(&body[open + 1..body.len() - 1])),
+        _ => (body, None),
+    };
+
+    match (name, subscript) {
+        ("#", None) => return Expansion::Text(var::positional_args().len().to_string()),
+        ("@", None) => return Expansion::Fields(var::positional_args()),
+        ("*", None) => return Expansion::Text(var::positional_args().join(" ")),
+        _ => {}
+    }
+
+    match subscript {
+        Some(all @ ("@" | "*")) => {
+            let values = if keys {
+                var::keys(name)
+            } else {
+                var::values(name)
+            };
+            if length {
+                Expansion::Text(values.len().to_string())
+            } else if all == "@" {
+                Expansion::Fields(values)
+            } else {
+                Expansion::Text(values.join(" "))
+            }
+        }
+        Some(index) => {
+            let index = expand_env_vars(index);
+            let value = var::get_element(name, index.trim()).unwrap_or_default();
+            if length {
+                Expansion::Text(value.chars().count().to_string())
+            } else {
+                Expansion::Text(value)
+            }
+        }
+        None => {
+            // ${10} 等多位数的位置参数
+            let value = match name.parse::<usize>() {
+                Ok(index) => var::positional(index),
+                Err(_) => var::get(name),
+            }
+            .unwrap_or_default();
+            if length {
+                Expansion::Text(value.chars().count().to_string())
+            } else {
+                Expansion::Text(value)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::token::{tokenize, Token};
+    use super::expand_word;
+    use crate::token::{Token, tokenize};
 
     #[test]
     fn test_env_expand_basic() {
@@ -130,4 +266,44 @@ mod tests {
         ];
         assert_eq!(tokens, expected_tokens);
     }
+
+    #[test]
+    fn test_env_expand_tilde_prefixes() {
+        let pwd = crate::builtin::cd::logical_pwd().display().to_string();
+        assert_eq!(expand_word("~0/src"), vec![format!("{}/src", pwd)]);
+        assert_eq!(expand_word("~-0"), vec![pwd]);
+        // 超出目录栈范围或无法识别的前缀保持原样
+        assert_eq!(expand_word("~9/x"), vec!["~9/x"]);
+        assert_eq!(expand_word("~nobody-here/x"), vec!["~nobody-here/x"]);
+        assert_eq!(expand_word("a~0"), vec!["a~0"]);
+    }
+
+    #[test]
+    fn test_env_expand_arrays() {
+        crate::var::set_array("ARR_TEST", &["a".into(), "b c".into(), "d".into()], false).unwrap();
+        let tokens =
+            tokenize("echo ${ARR_TEST[1]} ${ARR_TEST[-1]} ${#ARR_TEST[@]} x${ARR_TEST[@]}y");
+        let expected_tokens = vec![
+            Token::Word("echo".to_string()),
+            Token::Word("b c".to_string()),
+            Token::Word("d".to_string()),
+            Token::Word("3".to_string()),
+            Token::Word("xa".to_string()),
+            Token::Word("b c".to_string()),
+            Token::Word("dy".to_string()),
+        ];
+        assert_eq!(tokens, expected_tokens);
+
+        crate::var::declare("MAP_TEST", true).unwrap();
+        crate::var::set_element("MAP_TEST", "k1", "v1", false).unwrap();
+        crate::var::set_element("MAP_TEST", "k2", "v2", false).unwrap();
+        let tokens = tokenize("echo ${!MAP_TEST[@]} ${MAP_TEST[k2]} ${EMPTY_ARR_TEST[@]}");
+        let expected_tokens = vec![
+            Token::Word("echo".to_string()),
+            Token::Word("k1".to_string()),
+            Token::Word("k2".to_string()),
+            Token::Word("v2".to_string()),
+        ];
+        assert_eq!(tokens, expected_tokens);
+    }
 }
@@ -1,15 +1,40 @@
 use crate::Result;
+use crate::{alias, var};
 mod env;
-use env::expand_env_vars;
+mod program;
+pub use env::expand_env_vars;
+use env::expand_word;
+pub use program::{Command, Connector, List, ParseError, parse_program};
 
 // 表示一个最小的词法单元
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Word(String),
+    Assignment(Assignment),
     Pipe,
     RedirectIn,
     RedirectOut,
     RedirectAppend,
+    // 命令列表的分隔符：`;`、换行、`&&`、`||`
+    Semi,
+    Newline,
+    And,
+    Or,
+}
+
+// 命令开头的变量赋值：`name=v`、`name+=v`、`name[i]=v`、`name=(a b)`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Assignment {
+    pub name: String,
+    pub index: Option<String>,
+    pub append: bool,
+    pub value: AssignValue,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AssignValue {
+    Scalar(String),
+    Array(Vec<String>),
 }
 
 // 表示一个执行单元的抽象语法树 (AST) 节点
@@ -18,6 +43,8 @@ pub enum CommandPart {
     Execute {
         name: String,
         args: Vec<String>,
+        // 仅有赋值而没有命令时 name 为空
+        assignments: Vec<Assignment>,
         stdin: ExecutionSource,
         stdout: ExecutionSource,
     },
@@ -36,78 +63,399 @@ pub enum PipeEndpoint {
     Write,
 }
 
+/// 词法分析并立即展开，仅用于测试；执行时在每条命令运行前才展开
+#[cfg(test)]
 pub fn tokenize(input: &str) -> Vec<Token> {
-    let mut tokens = Vec::new();
-    let mut chars = input.chars().peekable();
-    let mut current = String::new();
-    let mut in_quotes = false;
+    expand(lex(input).0)
+}
 
-    while let Some(c) = chars.next() {
-        match c {
-            '"' => {
-                in_quotes = !in_quotes;
-                // 注意：在最终的 Word 中去除引号
-                if !in_quotes && !current.is_empty() {
-                    tokens.push(Token::Word(current.to_string()));
-                    current.clear();
+/// 词法分析但不做展开，同时返回输入是否完整（引号与数组括号均已闭合、结尾没有续行符）
+pub fn lex(input: &str) -> (Vec<Token>, bool) {
+    let mut lexer = Lexer::default();
+    let complete = lexer.run(input);
+    (lexer.tokens, complete)
+}
+
+#[derive(Default)]
+struct Lexer {
+    tokens: Vec<Token>,
+    current: String,
+    // 当前单词是否含有引号或转义，这样的单词不做别名展开，`""` 也会成为空单词
+    quoted: bool,
+    // 位于 `[[ ... ]]` 内部时，`<`、`>`、`|`、`&&` 作为普通字符
+    in_cond: bool,
+    // 上一个别名的值以空白结尾时，下一个单词也要检查别名
+    alias_next: bool,
+    // 正在展开的别名，避免 `alias ls='ls -F'` 这样的递归
+    expanding: Vec<String>,
+}
+
+impl Lexer {
+    fn run(&mut self, input: &str) -> bool {
+        let mut chars = input.chars().peekable();
+        let mut in_quotes = false;
+        let mut complete = true;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    in_quotes = !in_quotes;
+                    self.quoted = true;
+                    // 注意：在最终的 Word 中去除引号
+                    if !in_quotes {
+                        self.finish_word();
+                    }
                 }
-            }
-            // 遇到非引号内的空格，作为分隔符
-            ' ' if !in_quotes => {
-                if !current.is_empty() {
-                    tokens.push(Token::Word(current.to_string()));
-                    current.clear();
+                // 单引号中的内容原样保留，转义展开时会处理的字符
+                '\'' if !in_quotes => {
+                    self.quoted = true;
+                    let mut closed = false;
+                    for nc in chars.by_ref() {
+                        if nc == '\'' {
+                            closed = true;
+                            break;
+                        }
+                        self.push_quoted(nc);
+                    }
+                    complete &= closed;
                 }
-            }
-            // 遇到操作符，作为分隔符
-            '|' | '<' | '>' if !in_quotes => {
-                if !current.is_empty() {
-                    tokens.push(Token::Word(current.to_string()));
-                    current.clear();
-                }
-
-                // 识别多字符操作符
-                match c {
-                    '|' => tokens.push(Token::Pipe),
-                    '<' => tokens.push(Token::RedirectIn),
-                    '>' => {
-                        if chars.peek() == Some(&'>') {
-                            chars.next(); // 消耗第二个 '>'
-                            tokens.push(Token::RedirectAppend);
-                        } else {
-                            tokens.push(Token::RedirectOut);
+                // 行尾的反斜杠表示续行
+                '\\' if !in_quotes && matches!(chars.peek(), Some('\n') | None) => {
+                    if chars.next().is_none() || chars.peek().is_none() {
+                        complete = false;
+                    }
+                }
+                // 引号外的反斜杠使下一个字符失去特殊含义
+                '\\' if !in_quotes => {
+                    if let Some(nc) = chars.next() {
+                        self.quoted = true;
+                        self.push_quoted(nc);
+                    }
+                }
+                // 双引号中只有 `\"`、`\\`、`\$` 是转义
+                '\\' if matches!(chars.peek(), Some('"' | '\\' | '$')) => {
+                    let nc = chars.next().unwrap_or_default();
+                    push_literal(&mut self.current, nc);
+                }
+                // 双引号中的 `~` 不展开为主目录
+                '~' if in_quotes => push_literal(&mut self.current, c),
+                // 单词开头的 `#` 表示注释，直到行尾
+                '#' if !in_quotes && self.current.is_empty() && !self.quoted => {
+                    while chars.next_if(|&nc| nc != '\n').is_some() {}
+                }
+                // 数组赋值 `name=(...)`，也可以是 `declare`/`typeset` 的参数
+                '(' if !in_quotes
+                    && (at_command_start(&self.tokens) || in_declaration(&self.tokens))
+                    && self.current.ends_with('=')
+                    && let Some((name, None, append, _)) = split_assignment(&self.current) =>
+                {
+                    let (elements, closed) = read_array_literal(&mut chars);
+                    complete &= closed;
+                    self.tokens.push(Token::Assignment(Assignment {
+                        name: name.to_string(),
+                        index: None,
+                        append,
+                        value: AssignValue::Array(elements),
+                    }));
+                    self.current.clear();
+                    self.quoted = false;
+                }
+                // 遇到非引号内的空白，作为分隔符
+                ' ' | '\t' if !in_quotes => self.finish_word(),
+                // 命令列表的分隔符
+                '\n' | ';' if !in_quotes => {
+                    self.finish_word();
+                    self.tokens.push(if c == ';' {
+                        Token::Semi
+                    } else {
+                        Token::Newline
+                    });
+                }
+                '&' if !in_quotes && !self.in_cond && chars.peek() == Some(&'&') => {
+                    self.finish_word();
+                    chars.next();
+                    self.tokens.push(Token::And);
+                }
+                // 遇到操作符，作为分隔符
+                '|' | '<' | '>' if !in_quotes && !self.in_cond => {
+                    self.finish_word();
+
+                    // 识别多字符操作符
+                    match c {
+                        '|' => {
+                            if chars.next_if_eq(&'|').is_some() {
+                                self.tokens.push(Token::Or);
+                            } else {
+                                self.tokens.push(Token::Pipe);
+                            }
+                        }
+                        '<' => self.tokens.push(Token::RedirectIn),
+                        '>' => {
+                            if chars.peek() == Some(&'>') {
+                                chars.next(); // 消耗第二个 '>'
+                                self.tokens.push(Token::RedirectAppend);
+                            } else {
+                                self.tokens.push(Token::RedirectOut);
+                            }
                         }
+                        _ => unreachable!(),
                     }
-                    _ => unreachable!(),
                 }
-            }
-            _ => {
-                current.push(c);
+                _ if in_quotes => {
+                    self.escape_pattern(c);
+                    self.current.push(c);
+                }
+                _ => {
+                    self.current.push(c);
+                }
             }
         }
+
+        // 处理循环结束时剩余的 current
+        self.finish_word();
+
+        complete && !in_quotes
+    }
+
+    /// 追加单引号中或转义的字符
+    fn push_quoted(&mut self, c: char) {
+        self.escape_pattern(c);
+        push_literal(&mut self.current, c);
     }
 
-    // 处理循环结束时剩余的 current
-    if !current.is_empty() {
-        tokens.push(Token::Word(current.to_string()));
+    /// 引号中或转义的通配符位于 `[[ ]]` 中 `==`、`=`、`!=` 右边的模式里时先加上反斜杠，按字面匹配
+    fn escape_pattern(&mut self, c: char) {
+        let in_pattern = self.in_cond
+            && matches!(self.tokens.last(), Some(Token::Word(op)) if matches!(op.as_str(), "==" | "=" | "!="));
+        if in_pattern && matches!(c, '*' | '?' | '[') {
+            self.current.push('\\');
+        }
     }
 
-    // 最终清理：去除 Word token 周围的引号（如果存在）
+    /// 结束当前单词：命令位置上的别名被替换为其值的记号，
+    /// 并跟踪是否进入或离开命令位置的 `[[ ... ]]`
+    fn finish_word(&mut self) {
+        if self.current.is_empty() && !self.quoted {
+            return;
+        }
+        let word = std::mem::take(&mut self.current);
+        let quoted = std::mem::take(&mut self.quoted);
+        let at_command_start = at_command_start(&self.tokens);
+
+        let check_alias = !quoted && !self.in_cond && (at_command_start || self.alias_next);
+        self.alias_next = false;
+        if check_alias
+            && !self.expanding.contains(&word)
+            && let Some(value) = alias::get(&word)
+        {
+            let mut expanding = self.expanding.clone();
+            expanding.push(word);
+            let mut nested = Lexer {
+                expanding,
+                ..Lexer::default()
+            };
+            nested.run(&value);
+            self.tokens.append(&mut nested.tokens);
+            self.in_cond = nested.in_cond;
+            self.alias_next = value.ends_with([' ', '\t']);
+            return;
+        }
+
+        if word == "[[" && at_command_start {
+            self.in_cond = true;
+        } else if word == "]]" {
+            self.in_cond = false;
+        }
+        if at_command_start
+            && !self.in_cond
+            && let Some((name, index, append, value)) = split_assignment(&word)
+        {
+            let assignment = Assignment {
+                name: name.to_string(),
+                index: index.map(str::to_string),
+                append,
+                value: AssignValue::Scalar(value.to_string()),
+            };
+            self.tokens.push(Token::Assignment(assignment));
+            return;
+        }
+        self.tokens.push(Token::Word(word));
+    }
+}
+
+/// 追加一个字面字符，展开时才有特殊含义的字符以反斜杠转义
+fn push_literal(current: &mut String, c: char) {
+    if matches!(c, '$' | '\\' | '~') {
+        current.push('\\');
+    }
+    current.push(c);
+}
+
+/// 展开 Word 与赋值中的变量
+pub fn expand(tokens: Vec<Token>) -> Vec<Token> {
     tokens
         .into_iter()
-        .map(|token| {
-            if let Token::Word(s) = token {
-                let trimmed = s.trim_matches('"').to_string();
-                Token::Word(expand_env_vars(&trimmed))
-            } else {
-                token
-            }
+        .flat_map(|token| match token {
+            // 引号产生的空单词原样保留
+            Token::Word(s) if s.is_empty() => vec![Token::Word(s)],
+            Token::Word(s) => expand_word(&s).into_iter().map(Token::Word).collect(),
+            Token::Assignment(assignment) => vec![Token::Assignment(Assignment {
+                index: assignment.index.as_deref().map(expand_env_vars),
+                value: match assignment.value {
+                    AssignValue::Scalar(value) => AssignValue::Scalar(expand_env_vars(&value)),
+                    AssignValue::Array(elements) => AssignValue::Array(
+                        elements
+                            .iter()
+                            .flat_map(|e| match e.is_empty() {
+                                // 引号中的空元素原样保留
+                                true => vec![String::new()],
+                                false => expand_word(e),
+                            })
+                            .collect(),
+                    ),
+                },
+                ..assignment
+            })],
+            _ => vec![token],
         })
         .collect()
 }
+
+/// 下一个单词是否位于命令名的位置（赋值可以出现在命令名之前）
+fn at_command_start(tokens: &[Token]) -> bool {
+    match tokens.last() {
+        None
+        | Some(
+            Token::Pipe
+            | Token::Assignment(_)
+            | Token::Semi
+            | Token::Newline
+            | Token::And
+            | Token::Or,
+        ) => true,
+        Some(Token::Word(word)) => program::is_command_prefix_keyword(word),
+        _ => false,
+    }
+}
+
+/// 当前的简单命令是否是 `declare`/`typeset`，其参数中的 `name=(...)` 与赋值一样作为数组字面量
+fn in_declaration(tokens: &[Token]) -> bool {
+    let start = tokens
+        .iter()
+        .rposition(|token| {
+            matches!(
+                token,
+                Token::Pipe | Token::Semi | Token::Newline | Token::And | Token::Or
+            )
+        })
+        .map_or(0, |i| i + 1);
+    tokens[start..]
+        .iter()
+        .find_map(|token| match token {
+            Token::Word(word) if !program::is_command_prefix_keyword(word) => Some(word),
+            _ => None,
+        })
+        .is_some_and(|name| name == "declare" || name == "typeset")
+}
+
+/// 命令参数中的数组赋值以 `name=(` 开头、`\0)` 结尾，每个元素之前有一个 `\0`，
+/// 这样 `declare` 能够区分数组字面量与引号中的 `(...)`
+const ARRAY_SEPARATOR: char = '\0';
+
+impl Assignment {
+    /// 作为命令参数传递的形式，见 [`split_array_argument`]
+    fn to_argument(&self) -> String {
+        let mut argument = format!("{}{}=", self.name, if self.append { "+" } else { "" });
+        match &self.value {
+            AssignValue::Scalar(value) => argument.push_str(value),
+            AssignValue::Array(elements) => {
+                argument.push('(');
+                for element in elements {
+                    argument.push(ARRAY_SEPARATOR);
+                    argument.push_str(element);
+                }
+                argument.push(ARRAY_SEPARATOR);
+                argument.push(')');
+            }
+        }
+        argument
+    }
+}
+
+/// 取出 [`Assignment::to_argument`] 编码的数组元素，`value` 不是数组字面量时返回 `None`
+pub fn split_array_argument(value: &str) -> Option<Vec<String>> {
+    let elements = value
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .strip_suffix(ARRAY_SEPARATOR)?;
+    Some(
+        elements
+            .split(ARRAY_SEPARATOR)
+            .skip(1)
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// 拆分 `name[index]+=value`，返回 (name, index, 是否追加, value)
+fn split_assignment(word: &str) -> Option<(&str, Option<&str>, bool, &str)> {
+    let eq = word.find('=')?;
+    let (lhs, value) = (&word[..eq], &word[eq + 1..]);
+    let (lhs, append) = match lhs.strip_suffix('+') {
+        Some(lhs) => (lhs, true),
+        None => (lhs, false),
+    };
+    let (name, index) = match lhs.find('[') {
+        Some(open) if lhs.ends_with(']') => (&lhs[..open], Some(&lhs[open + 1..lhs.len() - 1])),
+        _ => (lhs, None),
+    };
+    var::is_valid_name(name).then_some((name, index, append, value))
+}
+
+/// 读取 `(` 之后直到 `)` 的数组元素，元素以空白分隔，可使用单引号或双引号；
+/// 同时返回是否遇到了闭合的 `)`
+fn read_array_literal(chars: &mut std::iter::Peekable<std::str::Chars>) -> (Vec<String>, bool) {
+    let mut elements = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut in_single = false;
+    // 区分空字符串元素 `""` 与元素之间的多余空格
+    let mut quoted = false;
+    let mut closed = false;
+    for c in chars.by_ref() {
+        match c {
+            '\'' if !in_quotes => {
+                in_single = !in_single;
+                quoted = true;
+            }
+            _ if in_single => push_literal(&mut current, c),
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            ')' if !in_quotes => {
+                closed = true;
+                break;
+            }
+            ' ' | '\t' | '\n' if !in_quotes => {
+                if !current.is_empty() || quoted {
+                    elements.push(std::mem::take(&mut current));
+                }
+                quoted = false;
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() || quoted {
+        elements.push(current);
+    }
+    (elements, closed)
+}
+
 pub fn parse_command_chain(tokens: Vec<Token>) -> Result<Vec<CommandPart>> {
     let mut parts = Vec::new();
     let mut current_command: Vec<String> = Vec::new();
+    let mut assignments: Vec<Assignment> = Vec::new();
     let mut iter = tokens.into_iter().peekable();
 
     // 状态机：跟踪下一个操作符需要什么
@@ -121,6 +469,16 @@ pub fn parse_command_chain(tokens: Vec<Token>) -> Result<Vec<CommandPart>> {
             Token::Word(word) => {
                 current_command.push(word);
             }
+            // 命令名之后的赋值是 `declare` 的参数
+            Token::Assignment(assignment) if !current_command.is_empty() => {
+                current_command.push(assignment.to_argument());
+            }
+            Token::Assignment(assignment) => {
+                assignments.push(assignment);
+            }
+            op @ (Token::Semi | Token::Newline | Token::And | Token::Or) => {
+                return Err(format!("Parse error: Unexpected list operator {:?}", op).into());
+            }
             op @ (Token::Pipe | Token::RedirectIn | Token::RedirectOut | Token::RedirectAppend) => {
                 // 1. 检查是否有前一个命令需要封装
                 if current_command.is_empty() {
@@ -139,6 +497,7 @@ pub fn parse_command_chain(tokens: Vec<Token>) -> Result<Vec<CommandPart>> {
                         parts.push(CommandPart::Execute {
                             name: current_command[0].clone(),
                             args: current_command.drain(1..).collect(),
+                            assignments: std::mem::take(&mut assignments),
                             stdin: pending_stdin,
                             stdout: pending_stdout,
                         });
@@ -179,6 +538,15 @@ pub fn parse_command_chain(tokens: Vec<Token>) -> Result<Vec<CommandPart>> {
         parts.push(CommandPart::Execute {
             name: current_command[0].clone(),
             args: current_command.drain(1..).collect(),
+            assignments,
+            stdin: pending_stdin,
+            stdout: pending_stdout,
+        });
+    } else if !assignments.is_empty() {
+        parts.push(CommandPart::Execute {
+            name: String::new(),
+            args: Vec::new(),
+            assignments,
             stdin: pending_stdin,
             stdout: pending_stdout,
         });
@@ -209,6 +577,7 @@ mod tests {
             args,
             stdin,
             stdout,
+            ..
         } = &parts[0];
 
         assert_eq!(name, "echo");
@@ -235,6 +604,7 @@ mod tests {
             args,
             stdin,
             stdout,
+            ..
         } = &parts[0];
         assert_eq!(name, "echo");
         assert_eq!(args, &vec!["123".to_string()]);
@@ -245,10 +615,134 @@ mod tests {
             args,
             stdin,
             stdout,
+            ..
         } = &parts[1];
         assert_eq!(name, "cat");
         assert!(args.is_empty());
         assert_eq!(*stdin, ExecutionSource::Pipe(PipeEndpoint::Read));
         assert_eq!(*stdout, ExecutionSource::Inherit);
     }
+
+    #[test]
+    fn test_token_extended_test() {
+        let input = "[[ a < b || $x =~ ^(c|d)$ ]] > out";
+        let tokens = tokenize(input);
+        let expected_tokens = vec![
+            Token::Word("[[".to_string()),
+            Token::Word("a".to_string()),
+            Token::Word("<".to_string()),
+            Token::Word("b".to_string()),
+            Token::Word("||".to_string()),
+            Token::Word("".to_string()),
+            Token::Word("=~".to_string()),
+            Token::Word("^(c|d)$".to_string()),
+            Token::Word("]]".to_string()),
+            Token::RedirectOut,
+            Token::Word("out".to_string()),
+        ];
+        assert_eq!(tokens, expected_tokens);
+    }
+
+    #[test]
+    fn test_token_assignment() {
+        let tokens = tokenize("arr+=(a \"b c\" \"\") map[k]=v FOO=bar env");
+        let expected_tokens = vec![
+            Token::Assignment(Assignment {
+                name: "arr".to_string(),
+                index: None,
+                append: true,
+                value: AssignValue::Array(vec!["a".into(), "b c".into(), "".into()]),
+            }),
+            Token::Assignment(Assignment {
+                name: "map".to_string(),
+                index: Some("k".to_string()),
+                append: false,
+                value: AssignValue::Scalar("v".to_string()),
+            }),
+            Token::Assignment(Assignment {
+                name: "FOO".to_string(),
+                index: None,
+                append: false,
+                value: AssignValue::Scalar("bar".to_string()),
+            }),
+            Token::Word("env".to_string()),
+        ];
+        assert_eq!(tokens, expected_tokens);
+        let parts = parse_command_chain(tokens).unwrap();
+        let CommandPart::Execute {
+            name, assignments, ..
+        } = &parts[0];
+        assert_eq!(name, "env");
+        assert_eq!(assignments.len(), 3);
+
+        // 非命令位置的 `=` 只是普通单词
+        let tokens = tokenize("echo a=b");
+        assert_eq!(tokens[1], Token::Word("a=b".to_string()));
+        let tokens = tokenize("echo a=(b)");
+        assert_eq!(tokens[1], Token::Word("a=(b)".to_string()));
+    }
+
+    #[test]
+    fn test_token_declare_array() {
+        let tokens = tokenize("declare -A m=([a]=1 [b]=\"2 3\") n=(); echo x=(y)");
+        let parts = parse_command_chain(tokens[..4].to_vec()).unwrap();
+        let CommandPart::Execute { name, args, .. } = &parts[0];
+        assert_eq!(name, "declare");
+        assert_eq!(args[0], "-A");
+        let (m, value) = args[1].split_once('=').unwrap();
+        assert_eq!(m, "m");
+        assert_eq!(
+            split_array_argument(value),
+            Some(vec!["[a]=1".to_string(), "[b]=2 3".to_string()])
+        );
+        assert_eq!(split_array_argument(&args[2][2..]), Some(vec![]));
+        // 引号中的 `(...)` 不是数组字面量
+        assert_eq!(split_array_argument("(1 2)"), None);
+        assert_eq!(tokens[6], Token::Word("x=(y)".to_string()));
+    }
+
+    #[test]
+    fn test_token_alias() {
+        crate::alias::set("t_ll", "t_ls -d");
+        crate::alias::set("t_ls", "t_ls -F");
+        crate::alias::set("t_e", "echo ");
+        crate::alias::set("t_r1", "t_r2");
+        crate::alias::set("t_r2", "t_r1 x");
+        let words = |input: &str| -> Vec<String> {
+            tokenize(input)
+                .into_iter()
+                .filter_map(|token| match token {
+                    Token::Word(word) => Some(word),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        assert_eq!(words("t_ll /tmp"), vec!["t_ls", "-F", "-d", "/tmp"]);
+        // 值以空格结尾时，下一个单词也检查别名
+        assert_eq!(words("t_e t_ll"), vec!["echo", "t_ls", "-F", "-d"]);
+        assert_eq!(
+            words("echo t_ll; t_ll"),
+            vec!["echo", "t_ll", "t_ls", "-F", "-d"]
+        );
+        // 正在展开的别名不会再次展开
+        assert_eq!(words("t_r1"), vec!["t_r1", "x"]);
+        // 引号或反斜杠可以绕过别名
+        assert_eq!(words("\\t_ll"), vec!["t_ll"]);
+        assert_eq!(words("'t_ll' x"), vec!["t_ll", "x"]);
+    }
+
+    #[test]
+    fn test_token_single_quotes() {
+        let tokens = tokenize("echo '$HOME \\n' 'it'\\''s' ''");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word("echo".to_string()),
+                Token::Word("$HOME \\n".to_string()),
+                Token::Word("it's".to_string()),
+                Token::Word("".to_string()),
+            ]
+        );
+    }
 }
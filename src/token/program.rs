@@ -0,0 +1,382 @@
+use super::{Token, lex};
+use crate::var;
+use std::fmt;
+use std::sync::Arc;
+
+/// 命令列表：每条命令记录它与前一条命令之间的连接方式
+pub type List = Vec<(Connector, Command)>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connector {
+    /// `;` 或换行：总是执行
+    Seq,
+    /// `&&`：前一条命令成功时执行
+    And,
+    /// `||`：前一条命令失败时执行
+    Or,
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// 简单命令或管道，保存未展开的记号，执行时再展开
+    Simple(Vec<Token>),
+    /// `if list; then list; [elif list; then list;]... [else list;] fi`
+    If {
+        branches: Vec<(List, List)>,
+        otherwise: Option<List>,
+    },
+    /// `{ list; }`
+    Group(List),
+    /// `name() { list; }` 或 `function name { list; }`
+    Function { name: String, body: Arc<List> },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    /// 输入在一条命令的中间结束，需要继续读取
+    Incomplete,
+    Syntax(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Incomplete => write!(f, "Parse error: unexpected end of file"),
+            ParseError::Syntax(message) => write!(f, "Parse error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// 这些保留字之后的单词仍处于命令名的位置
+pub(super) fn is_command_prefix_keyword(word: &str) -> bool {
+    matches!(word, "if" | "then" | "elif" | "else" | "{")
+}
+
+/// 解析完整的输入；输入不完整时返回 `ParseError::Incomplete`，调用方可以继续读取下一行后重试
+pub fn parse_program(input: &str) -> Result<List, ParseError> {
+    let (tokens, complete) = lex(input);
+    let mut parser = Parser { tokens, pos: 0 };
+    let list = parser.parse_list(&[])?;
+    if !complete {
+        return Err(ParseError::Incomplete);
+    }
+    Ok(list)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Token::Newline) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect_word(&mut self, expected: &str) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(Token::Word(word)) if word == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(token) => Err(unexpected(token)),
+            None => Err(ParseError::Incomplete),
+        }
+    }
+
+    /// 解析命令列表，直到输入结束或在命令位置遇到 `terminators` 中的保留字（不消耗）
+    fn parse_list(&mut self, terminators: &[&str]) -> Result<List, ParseError> {
+        let mut list = List::new();
+        let mut connector = Connector::Seq;
+        loop {
+            while matches!(self.peek(), Some(Token::Newline | Token::Semi)) {
+                if self.peek() == Some(&Token::Semi) && list.is_empty() {
+                    return Err(unexpected(&Token::Semi));
+                }
+                self.pos += 1;
+            }
+            let Some(token) = self.peek() else {
+                return if terminators.is_empty() {
+                    Ok(list)
+                } else {
+                    Err(ParseError::Incomplete)
+                };
+            };
+            if let Token::Word(word) = token
+                && terminators.contains(&word.as_str())
+            {
+                return Ok(list);
+            }
+
+            let command = self.parse_command()?;
+            list.push((connector, command));
+
+            connector = match self.peek() {
+                Some(Token::And) => Connector::And,
+                Some(Token::Or) => Connector::Or,
+                Some(Token::Semi | Token::Newline) | None => {
+                    connector = Connector::Seq;
+                    continue;
+                }
+                Some(token) => return Err(unexpected(token)),
+            };
+            // `&&` 与 `||` 之后允许换行，但必须跟着一条命令
+            self.pos += 1;
+            self.skip_newlines();
+            match self.peek() {
+                None => return Err(ParseError::Incomplete),
+                Some(token @ (Token::Semi | Token::And | Token::Or)) => {
+                    return Err(unexpected(token));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn parse_command(&mut self) -> Result<Command, ParseError> {
+        match self.peek_word() {
+            Some("if") => return self.parse_if(),
+            Some("{") => {
+                self.pos += 1;
+                return Ok(Command::Group(self.parse_group_body()?));
+            }
+            Some("function") => {
+                self.pos += 1;
+                let name = match self.peek_word() {
+                    Some(word) => word.strip_suffix("()").unwrap_or(word).to_string(),
+                    None => return Err(self.unexpected_here()),
+                };
+                self.pos += 1;
+                if self.peek_word() == Some("()") {
+                    self.pos += 1;
+                }
+                return self.parse_function_body(name);
+            }
+            Some(keyword @ ("then" | "elif" | "else" | "fi" | "}")) => {
+                return Err(ParseError::Syntax(format!(
+                    "syntax error near unexpected token `{}'",
+                    keyword
+                )));
+            }
+            Some(word) => {
+                if let Some(name) = word.strip_suffix("()")
+                    && var::is_valid_name(name)
+                {
+                    let name = name.to_string();
+                    self.pos += 1;
+                    return self.parse_function_body(name);
+                }
+                if var::is_valid_name(word)
+                    && matches!(self.tokens.get(self.pos + 1), Some(Token::Word(next)) if next == "()")
+                {
+                    let name = word.to_string();
+                    self.pos += 2;
+                    return self.parse_function_body(name);
+                }
+            }
+            None => {}
+        }
+        self.parse_simple()
+    }
+
+    /// 收集到列表分隔符为止的记号；行尾的 `|` 允许命令在下一行继续
+    fn parse_simple(&mut self) -> Result<Command, ParseError> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Semi | Token::And | Token::Or => break,
+                Token::Newline if tokens.last() == Some(&Token::Pipe) => self.pos += 1,
+                Token::Newline => break,
+                _ => {
+                    tokens.push(token.clone());
+                    self.pos += 1;
+                }
+            }
+        }
+        match tokens.last() {
+            None => Err(self.unexpected_here()),
+            Some(Token::Pipe) if self.peek().is_none() => Err(ParseError::Incomplete),
+            _ => Ok(Command::Simple(tokens)),
+        }
+    }
+
+    fn parse_if(&mut self) -> Result<Command, ParseError> {
+        self.expect_word("if")?;
+        let mut branches = Vec::new();
+        let mut otherwise = None;
+        loop {
+            let condition = self.parse_non_empty_list(&["then"])?;
+            self.expect_word("then")?;
+            let body = self.parse_non_empty_list(&["elif", "else", "fi"])?;
+            branches.push((condition, body));
+            match self.peek_word() {
+                Some("elif") => self.pos += 1,
+                Some("else") => {
+                    self.pos += 1;
+                    otherwise = Some(self.parse_non_empty_list(&["fi"])?);
+                    self.expect_word("fi")?;
+                    break;
+                }
+                _ => {
+                    self.expect_word("fi")?;
+                    break;
+                }
+            }
+        }
+        Ok(Command::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    fn parse_function_body(&mut self, name: String) -> Result<Command, ParseError> {
+        self.skip_newlines();
+        self.expect_word("{")?;
+        let body = self.parse_group_body()?;
+        Ok(Command::Function {
+            name,
+            body: Arc::new(body),
+        })
+    }
+
+    /// `{` 之后直到 `}` 的部分
+    fn parse_group_body(&mut self) -> Result<List, ParseError> {
+        let body = self.parse_non_empty_list(&["}"])?;
+        self.expect_word("}")?;
+        Ok(body)
+    }
+
+    fn parse_non_empty_list(&mut self, terminators: &[&str]) -> Result<List, ParseError> {
+        let list = self.parse_list(terminators)?;
+        if list.is_empty() {
+            return Err(self.unexpected_here());
+        }
+        Ok(list)
+    }
+
+    fn unexpected_here(&self) -> ParseError {
+        match self.peek() {
+            Some(token) => unexpected(token),
+            None => ParseError::Incomplete,
+        }
+    }
+}
+
+fn unexpected(token: &Token) -> ParseError {
+    let text = match token {
+        Token::Word(word) => word.as_str(),
+        Token::Assignment(assignment) => assignment.name.as_str(),
+        Token::Pipe => "|",
+        Token::RedirectIn => "<",
+        Token::RedirectOut => ">",
+        Token::RedirectAppend => ">>",
+        Token::Semi => ";",
+        Token::Newline => "newline",
+        Token::And => "&&",
+        Token::Or => "||",
+    };
+    ParseError::Syntax(format!("syntax error near unexpected token `{}'", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simple_words(command: &Command) -> Vec<String> {
+        match command {
+            Command::Simple(tokens) => tokens
+                .iter()
+                .filter_map(|token| match token {
+                    Token::Word(word) => Some(word.clone()),
+                    _ => None,
+                })
+                .collect(),
+            other => panic!("expected simple command, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_connectors() {
+        let list = parse_program("true && echo a || echo b; echo c\necho d").unwrap();
+        let connectors: Vec<Connector> = list.iter().map(|(c, _)| *c).collect();
+        assert_eq!(
+            connectors,
+            vec![
+                Connector::Seq,
+                Connector::And,
+                Connector::Or,
+                Connector::Seq,
+                Connector::Seq
+            ]
+        );
+        assert_eq!(simple_words(&list[3].1), vec!["echo", "c"]);
+    }
+
+    #[test]
+    fn test_parse_if_and_function() {
+        let input = "greet() {\n  if [ -n \"$1\" ]; then\n    echo hi $1\n  elif true; then echo x\n  else\n    echo nobody\n  fi\n}";
+        let list = parse_program(input).unwrap();
+        assert_eq!(list.len(), 1);
+        let Command::Function { name, body } = &list[0].1 else {
+            panic!("expected function");
+        };
+        assert_eq!(name, "greet");
+        let Command::If {
+            branches,
+            otherwise,
+        } = &body[0].1
+        else {
+            panic!("expected if");
+        };
+        assert_eq!(branches.len(), 2);
+        assert_eq!(simple_words(&branches[0].1[0].1), vec!["echo", "hi", "$1"]);
+        assert!(otherwise.is_some());
+    }
+
+    #[test]
+    fn test_parse_incomplete() {
+        for input in [
+            "if true; then",
+            "f() {",
+            "echo \"open",
+            "echo a |",
+            "true &&",
+            "echo a \\",
+            "arr=(a",
+            "function f",
+        ] {
+            assert_eq!(
+                parse_program(input).unwrap_err(),
+                ParseError::Incomplete,
+                "{}",
+                input
+            );
+        }
+        assert!(parse_program("echo a |\ncat").is_ok());
+        assert!(parse_program("echo \"a\nb\"").is_ok());
+        assert!(matches!(parse_program("fi"), Err(ParseError::Syntax(_))));
+        assert!(matches!(
+            parse_program("; echo"),
+            Err(ParseError::Syntax(_))
+        ));
+        assert!(matches!(
+            parse_program("if then fi"),
+            Err(ParseError::Syntax(_))
+        ));
+    }
+}
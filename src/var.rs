@@ -0,0 +1,367 @@
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::sync::RwLock;
+
+/// shell 变量的值，未在此处定义的变量回退到进程环境变量
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Scalar(String),
+    Indexed(BTreeMap<usize, String>),
+    Associative(BTreeMap<String, String>),
+}
+
+lazy_static! {
+    static ref VARS: RwLock<HashMap<String, Value>> = RwLock::new(HashMap::new());
+}
+
+/// 变量名须满足 `[A-Za-z_][A-Za-z0-9_]*`
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 取标量值；数组取下标 0 的元素
+pub fn get(name: &str) -> Option<String> {
+    match VARS.read().unwrap().get(name) {
+        Some(Value::Scalar(s)) => Some(s.clone()),
+        Some(Value::Indexed(map)) => map.get(&0).cloned(),
+        Some(Value::Associative(map)) => map.get("0").cloned(),
+        None => env::var(name).ok(),
+    }
+}
+
+pub fn is_set(name: &str) -> bool {
+    VARS.read().unwrap().contains_key(name) || env::var_os(name).is_some()
+}
+
+/// 取 `${name[index]}`，索引数组支持负数下标
+pub fn get_element(name: &str, index: &str) -> Option<String> {
+    match VARS.read().unwrap().get(name) {
+        Some(Value::Associative(map)) => map.get(index).cloned(),
+        Some(Value::Indexed(map)) => resolve_index(map, index).and_then(|i| map.get(&i).cloned()),
+        Some(Value::Scalar(s)) => (parse_index(index) == Some(0)).then(|| s.clone()),
+        None => {
+            let value = env::var(name).ok()?;
+            (parse_index(index) == Some(0)).then_some(value)
+        }
+    }
+}
+
+/// `${name[@]}` 的全部元素，按下标或键排序
+pub fn values(name: &str) -> Vec<String> {
+    match VARS.read().unwrap().get(name) {
+        Some(Value::Scalar(s)) => vec![s.clone()],
+        Some(Value::Indexed(map)) => map.values().cloned().collect(),
+        Some(Value::Associative(map)) => map.values().cloned().collect(),
+        None => env::var(name).into_iter().collect(),
+    }
+}
+
+/// `${!name[@]}` 的全部下标或键
+pub fn keys(name: &str) -> Vec<String> {
+    match VARS.read().unwrap().get(name) {
+        Some(Value::Scalar(_)) => vec!["0".to_string()],
+        Some(Value::Indexed(map)) => map.keys().map(usize::to_string).collect(),
+        Some(Value::Associative(map)) => map.keys().cloned().collect(),
+        None if env::var_os(name).is_some() => vec!["0".to_string()],
+        None => Vec::new(),
+    }
+}
+
+/// `name=value` 或 `name+=value`；已导出的环境变量直接更新环境
+pub fn set_scalar(name: &str, value: &str, append: bool) {
+    let mut vars = VARS.write().unwrap();
+    match vars.get_mut(name) {
+        Some(Value::Scalar(s)) => assign(s, value, append),
+        Some(Value::Indexed(map)) => assign(map.entry(0).or_default(), value, append),
+        Some(Value::Associative(map)) => {
+            assign(map.entry("0".to_string()).or_default(), value, append)
+        }
+        None => match env::var(name) {
+            Ok(mut current) => {
+                assign(&mut current, value, append);
+                unsafe {
+                    env::set_var(name, current);
+                }
+            }
+            Err(_) => {
+                vars.insert(name.to_string(), Value::Scalar(value.to_string()));
+            }
+        },
+    }
+}
+
+/// 删除 shell 变量与同名的环境变量
+pub fn unset(name: &str) {
+    VARS.write().unwrap().remove(name);
+    unsafe {
+        env::remove_var(name);
+    }
+}
+
+/// `unset name[index]`：删除数组的一个元素，标量只有下标 0 可以删除
+pub fn unset_element(name: &str, index: &str) -> Result<(), String> {
+    let mut vars = VARS.write().unwrap();
+    match vars.get_mut(name) {
+        Some(Value::Associative(map)) => {
+            map.remove(index);
+        }
+        Some(Value::Indexed(map)) => {
+            let i = resolve_index(map, index)
+                .ok_or_else(|| format!("{}[{}]: bad array subscript", name, index))?;
+            map.remove(&i);
+        }
+        Some(Value::Scalar(_)) if parse_index(index) == Some(0) => {
+            vars.remove(name);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 变量在某一时刻的 shell 变量值与同名的环境变量，用于临时覆盖变量后恢复
+pub struct Saved {
+    name: String,
+    value: Option<Value>,
+    exported: Option<std::ffi::OsString>,
+}
+
+pub fn save(name: &str) -> Saved {
+    Saved {
+        name: name.to_string(),
+        value: VARS.read().unwrap().get(name).cloned(),
+        exported: env::var_os(name),
+    }
+}
+
+/// 恢复 [`save`] 时的状态，期间设置的值被丢弃
+pub fn restore(saved: Saved) {
+    let Saved {
+        name,
+        value,
+        exported,
+    } = saved;
+    unsafe {
+        match exported {
+            Some(exported) => env::set_var(&name, exported),
+            None => env::remove_var(&name),
+        }
+    }
+    let mut vars = VARS.write().unwrap();
+    match value {
+        Some(value) => vars.insert(name, value),
+        None => vars.remove(&name),
+    };
+}
+
+/// 设置并导出到进程环境，覆盖同名的 shell 变量
+pub fn set_exported(name: &str, value: &str) {
+    VARS.write().unwrap().remove(name);
+    unsafe {
+        env::set_var(name, value);
+    }
+}
+
+/// `name[index]=value`，变量不存在或为标量时转换为索引数组
+pub fn set_element(name: &str, index: &str, value: &str, append: bool) -> Result<(), String> {
+    let mut vars = VARS.write().unwrap();
+    let entry = vars
+        .entry(name.to_string())
+        .or_insert_with(|| Value::Indexed(BTreeMap::new()));
+    if let Value::Scalar(s) = entry {
+        *entry = Value::Indexed(BTreeMap::from([(0, std::mem::take(s))]));
+    }
+    match entry {
+        Value::Associative(map) => assign(map.entry(index.to_string()).or_default(), value, append),
+        Value::Indexed(map) => {
+            let i = resolve_index(map, index)
+                .ok_or_else(|| format!("{}[{}]: bad array subscript", name, index))?;
+            assign(map.entry(i).or_default(), value, append)
+        }
+        Value::Scalar(_) => unreachable!(),
+    }
+    Ok(())
+}
+
+/// `name=(...)` 或 `name+=(...)`，元素可写作 `[key]=value`
+pub fn set_array(name: &str, elements: &[String], append: bool) -> Result<(), String> {
+    let mut vars = VARS.write().unwrap();
+    let entry = vars
+        .entry(name.to_string())
+        .or_insert_with(|| Value::Indexed(BTreeMap::new()));
+    match entry {
+        Value::Associative(map) => {
+            if !append {
+                map.clear();
+            }
+            for element in elements {
+                let (key, value) = split_keyed(element).ok_or_else(|| {
+                    format!(
+                        "{}: {}: must use subscript when assigning associative array",
+                        name, element
+                    )
+                })?;
+                map.insert(key.to_string(), value.to_string());
+            }
+        }
+        _ => {
+            let mut map = match std::mem::replace(entry, Value::Indexed(BTreeMap::new())) {
+                Value::Indexed(map) if append => map,
+                Value::Scalar(s) if append => BTreeMap::from([(0, s)]),
+                _ => BTreeMap::new(),
+            };
+            let mut next = map.keys().next_back().map_or(0, |i| i + 1);
+            for element in elements {
+                let (index, value) = match split_keyed(element) {
+                    Some((key, value)) => (
+                        resolve_index(&map, key)
+                            .ok_or_else(|| format!("{}[{}]: bad array subscript", name, key))?,
+                        value,
+                    ),
+                    None => (next, element.as_str()),
+                };
+                map.insert(index, value.to_string());
+                next = index + 1;
+            }
+            *entry = Value::Indexed(map);
+        }
+    }
+    Ok(())
+}
+
+/// `declare -a` / `declare -A`，已存在的同类变量保持不变
+pub fn declare(name: &str, associative: bool) -> Result<(), String> {
+    let mut vars = VARS.write().unwrap();
+    match vars.get(name) {
+        Some(Value::Associative(_)) if associative => Ok(()),
+        Some(Value::Indexed(_)) if !associative => Ok(()),
+        Some(Value::Indexed(_)) => Err(format!(
+            "{}: cannot convert indexed to associative array",
+            name
+        )),
+        Some(Value::Associative(_)) => Err(format!(
+            "{}: cannot convert associative to indexed array",
+            name
+        )),
+        current => {
+            let scalar = match current {
+                Some(Value::Scalar(s)) => Some(s.clone()),
+                _ => env::var(name).ok(),
+            };
+            let value = if associative {
+                Value::Associative(
+                    scalar
+                        .map(|s| BTreeMap::from([("0".to_string(), s)]))
+                        .unwrap_or_default(),
+                )
+            } else {
+                Value::Indexed(scalar.map(|s| BTreeMap::from([(0, s)])).unwrap_or_default())
+            };
+            vars.insert(name.to_string(), value);
+            Ok(())
+        }
+    }
+}
+
+/// 以 `declare -p` 的格式输出 shell 变量，未指定名称时输出全部
+pub fn describe(name: &str) -> Option<String> {
+    let vars = VARS.read().unwrap();
+    let line = match vars.get(name) {
+        Some(Value::Scalar(s)) => format!("declare -- {}={}", name, quote(s)),
+        Some(Value::Indexed(map)) => format!(
+            "declare -a {}=({})",
+            name,
+            map.iter()
+                .map(|(i, v)| format!("[{}]={}", i, quote(v)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+        Some(Value::Associative(map)) => format!(
+            "declare -A {}=({})",
+            name,
+            map.iter()
+                .map(|(k, v)| format!("[{}]={}", k, quote(v)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+        None => format!("declare -x {}={}", name, quote(&env::var(name).ok()?)),
+    };
+    Some(line)
+}
+
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = VARS.read().unwrap().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        if matches!(c, '"' | '\\' | '$') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+fn assign(slot: &mut String, value: &str, append: bool) {
+    if append {
+        slot.push_str(value);
+    } else {
+        *slot = value.to_string();
+    }
+}
+
+/// 拆分 `[key]=value` 形式的数组元素
+fn split_keyed(element: &str) -> Option<(&str, &str)> {
+    let rest = element.strip_prefix('[')?;
+    let end = rest.find("]=")?;
+    Some((&rest[..end], &rest[end + 2..]))
+}
+
+fn parse_index(index: &str) -> Option<i64> {
+    index.trim().parse().ok()
+}
+
+/// 负数下标从最大下标之后倒数
+fn resolve_index(map: &BTreeMap<usize, String>, index: &str) -> Option<usize> {
+    let i = parse_index(index)?;
+    if i >= 0 {
+        return Some(i as usize);
+    }
+    let len = map.keys().next_back().map_or(0, |max| max + 1) as i64;
+    usize::try_from(len + i).ok()
+}
+
+lazy_static! {
+    /// 位置参数，下标 0 为 `$0`
+    static ref POSITIONAL: RwLock<Vec<String>> =
+        RwLock::new(vec![env::args().next().unwrap_or_default()]);
+}
+
+/// `$0`、`$1` ...，`n` 超出范围时返回 `None`
+pub fn positional(n: usize) -> Option<String> {
+    POSITIONAL.read().unwrap().get(n).cloned()
+}
+
+/// `$@`，即 `$1` 起的全部位置参数
+pub fn positional_args() -> Vec<String> {
+    POSITIONAL.read().unwrap()[1..].to_vec()
+}
+
+pub fn set_arg0(name: &str) {
+    POSITIONAL.write().unwrap()[0] = name.to_string();
+}
+
+/// 替换 `$1` 起的位置参数，返回原先的参数以便恢复
+pub fn replace_positional_args(args: Vec<String>) -> Vec<String> {
+    let mut positional = POSITIONAL.write().unwrap();
+    let old = positional.split_off(1);
+    positional.extend(args);
+    old
+}